@@ -0,0 +1,512 @@
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::{format_ident, quote};
+use syn::{
+    parse_macro_input, parse_quote, Data, DeriveInput, Fields, Generics, Index, LitInt, LitStr,
+    Path,
+};
+
+#[proc_macro_derive(Serialize, attributes(sbs))]
+pub fn derive_serialize(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand_serialize(input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+#[proc_macro_derive(DeSerialize, attributes(sbs))]
+pub fn derive_deserialize(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand_deserialize(input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+#[proc_macro_derive(Describe, attributes(sbs))]
+pub fn derive_describe(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand_describe(input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+struct Container {
+    krate: Path,
+    tagged: bool,
+}
+
+fn container_attrs(input: &DeriveInput) -> syn::Result<Container> {
+    let mut container = Container {
+        krate: parse_quote!(::sbs_api_internal),
+        tagged: false,
+    };
+
+    for attr in input
+        .attrs
+        .iter()
+        .filter(|attr| attr.path().is_ident("sbs"))
+    {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("crate") {
+                let lit: LitStr = meta.value()?.parse()?;
+                container.krate = lit.parse()?;
+                Ok(())
+            } else if meta.path.is_ident("tagged") {
+                container.tagged = true;
+                Ok(())
+            } else {
+                Err(meta.error("unsupported sbs attribute"))
+            }
+        })?;
+    }
+
+    if container.tagged && !matches!(input.data, Data::Struct(_)) {
+        return Err(syn::Error::new_spanned(
+            &input.ident,
+            "only structs can use the tagged encoding",
+        ));
+    }
+
+    Ok(container)
+}
+
+struct TaggedField {
+    member: TokenStream2,
+    id: u32,
+    default: TokenStream2,
+}
+
+/// Field ids and defaults of a `#[sbs(tagged)]` struct. Every field must
+/// set `#[sbs(id = N)]`: ids taken from the field order would silently change
+/// when a field is removed or moved, and old files would then decode into the
+/// wrong fields.
+fn tagged_fields(fields: &Fields) -> syn::Result<Vec<TaggedField>> {
+    let mut tagged: Vec<TaggedField> = Vec::new();
+
+    for (i, field) in fields.iter().enumerate() {
+        let mut id = None;
+        let mut default = quote!(::core::default::Default::default());
+
+        for attr in field
+            .attrs
+            .iter()
+            .filter(|attr| attr.path().is_ident("sbs"))
+        {
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("id") {
+                    let lit: LitInt = meta.value()?.parse()?;
+                    id = Some(lit.base10_parse()?);
+                    Ok(())
+                } else if meta.path.is_ident("default") {
+                    let lit: LitStr = meta.value()?.parse()?;
+                    let path: Path = lit.parse()?;
+                    default = quote!(#path());
+                    Ok(())
+                } else {
+                    Err(meta.error("unsupported sbs field attribute"))
+                }
+            })?;
+        }
+
+        let Some(id) = id else {
+            return Err(syn::Error::new_spanned(
+                field,
+                "fields of a tagged struct need an id, like #[sbs(id = 1)]",
+            ));
+        };
+        if tagged.iter().any(|other| other.id == id) {
+            return Err(syn::Error::new_spanned(
+                field,
+                format!("duplicate field id {id}"),
+            ));
+        }
+
+        let member = match &field.ident {
+            Some(ident) => quote!(#ident),
+            None => {
+                let index = Index::from(i);
+                quote!(#index)
+            }
+        };
+        tagged.push(TaggedField {
+            member,
+            id,
+            default,
+        });
+    }
+
+    Ok(tagged)
+}
+
+/// Field attributes only mean something on tagged structs.
+fn reject_field_attrs(input: &DeriveInput) -> syn::Result<()> {
+    let fields: Vec<&syn::Field> = match &input.data {
+        Data::Struct(data) => data.fields.iter().collect(),
+        Data::Enum(data) => data.variants.iter().flat_map(|v| &v.fields).collect(),
+        Data::Union(_) => Vec::new(),
+    };
+
+    match fields
+        .iter()
+        .flat_map(|field| &field.attrs)
+        .find(|attr| attr.path().is_ident("sbs"))
+    {
+        Some(attr) => Err(syn::Error::new_spanned(
+            attr,
+            "sbs field attributes require #[sbs(tagged)] on the struct",
+        )),
+        None => Ok(()),
+    }
+}
+
+fn add_bounds(mut generics: Generics, bound: &Path) -> Generics {
+    for param in generics.type_params_mut() {
+        param.bounds.push(parse_quote!(#bound));
+    }
+    generics
+}
+
+fn field_bindings(fields: &Fields) -> Vec<syn::Ident> {
+    fields
+        .iter()
+        .enumerate()
+        .map(|(i, field)| match &field.ident {
+            Some(ident) => ident.clone(),
+            None => format_ident!("__field{}", i),
+        })
+        .collect()
+}
+
+fn destructure(fields: &Fields, bindings: &[syn::Ident]) -> TokenStream2 {
+    match fields {
+        Fields::Named(_) => quote!({ #(#bindings),* }),
+        Fields::Unnamed(_) => quote!(( #(#bindings),* )),
+        Fields::Unit => quote!(),
+    }
+}
+
+fn construct(fields: &Fields, krate: &Path) -> TokenStream2 {
+    match fields {
+        Fields::Named(named) => {
+            let names = named.named.iter().map(|field| &field.ident);
+            quote!({ #(#names: #krate::DeSerialize::deserialize(reader)?),* })
+        }
+        Fields::Unnamed(unnamed) => {
+            let values = unnamed
+                .unnamed
+                .iter()
+                .map(|_| quote!(#krate::DeSerialize::deserialize(reader)?));
+            quote!(( #(#values),* ))
+        }
+        Fields::Unit => quote!(),
+    }
+}
+
+/// Declares a `u64` const holding each variant's discriminant on the wire.
+///
+/// They are worked out as `i128`, so negative discriminants and the implicit
+/// ones after them count up the way the compiler does, then cast to `u64`
+/// keeping the low 64 bits: `-1` goes on the wire as `u64::MAX`.
+fn discriminant_consts(data: &syn::DataEnum) -> (TokenStream2, Vec<syn::Ident>) {
+    let mut consts = Vec::new();
+    let mut values: Vec<syn::Ident> = Vec::new();
+    let mut names = Vec::new();
+
+    for (i, variant) in data.variants.iter().enumerate() {
+        let value_name = format_ident!("__SBS_DISCRIMINANT_VALUE_{}", i);
+        let name = format_ident!("__SBS_DISCRIMINANT_{}", i);
+        let value = match (&variant.discriminant, values.last()) {
+            (Some((_, expr)), _) => quote!((#expr) as i128),
+            (None, Some(prev)) => quote!(#prev + 1),
+            (None, None) => quote!(0),
+        };
+        consts.push(quote! {
+            const #value_name: i128 = #value;
+            const #name: u64 = #value_name as u64;
+        });
+        values.push(value_name);
+        names.push(name);
+    }
+
+    (quote!(#(#consts)*), names)
+}
+
+fn expand_serialize(input: DeriveInput) -> syn::Result<TokenStream2> {
+    let Container { krate, tagged } = container_attrs(&input)?;
+    if !tagged {
+        reject_field_attrs(&input)?;
+    }
+    let name = &input.ident;
+    let generics = add_bounds(input.generics.clone(), &parse_quote!(#krate::Serialize));
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+
+    let body = match &input.data {
+        Data::Struct(data) if tagged => {
+            let fields = tagged_fields(&data.fields)?;
+            let count = fields.len() as u64;
+            let writes = fields.iter().map(|TaggedField { member, id, .. }| {
+                quote!(#krate::TaggedFields::write_field(#id, &self.#member, writer);)
+            });
+            quote! {
+                #krate::TaggedFields::write_count(#count, writer);
+                #(#writes)*
+            }
+        }
+        Data::Struct(data) => {
+            let writes = data.fields.iter().enumerate().map(|(i, field)| {
+                let member = match &field.ident {
+                    Some(ident) => quote!(#ident),
+                    None => {
+                        let index = Index::from(i);
+                        quote!(#index)
+                    }
+                };
+                quote!(#krate::Serialize::serialize(&self.#member, writer);)
+            });
+            quote!(#(#writes)*)
+        }
+        Data::Enum(data) => {
+            let (consts, discriminants) = discriminant_consts(data);
+            let arms = data.variants.iter().zip(&discriminants).map(|(variant, discriminant)| {
+                let ident = &variant.ident;
+                let bindings = field_bindings(&variant.fields);
+                let pattern = destructure(&variant.fields, &bindings);
+                quote! {
+                    Self::#ident #pattern => {
+                        #krate::Serialize::serialize(&#krate::Discriminant(#discriminant), writer);
+                        #(#krate::Serialize::serialize(#bindings, writer);)*
+                    }
+                }
+            });
+            // an enum without variants has no values to match on, and only a
+            // match on the place itself is accepted as exhaustive
+            let scrutinee = if data.variants.is_empty() {
+                quote!(*self)
+            } else {
+                quote!(self)
+            };
+            quote! {
+                #consts
+                match #scrutinee {
+                    #(#arms)*
+                }
+            }
+        }
+        Data::Union(data) => {
+            return Err(syn::Error::new_spanned(
+                data.union_token,
+                "Serialize cannot be derived for unions",
+            ))
+        }
+    };
+
+    Ok(quote! {
+        impl #impl_generics #krate::Serialize for #name #ty_generics #where_clause {
+            #[allow(unused_variables)]
+            fn serialize<__W: #krate::Writer + ?Sized>(&self, writer: &mut __W) {
+                #body
+            }
+        }
+    })
+}
+
+fn expand_deserialize(input: DeriveInput) -> syn::Result<TokenStream2> {
+    let Container { krate, tagged } = container_attrs(&input)?;
+    if !tagged {
+        reject_field_attrs(&input)?;
+    }
+    let name = &input.ident;
+    let (_, ty_generics, _) = input.generics.split_for_impl();
+
+    // the buffer lifetime must outlive every lifetime the type borrows for
+    let mut generics = add_bounds(
+        input.generics.clone(),
+        &parse_quote!(#krate::DeSerialize<'de>),
+    );
+    let borrowed = generics
+        .lifetimes()
+        .map(|param| param.lifetime.clone())
+        .collect::<Vec<_>>();
+    generics.params.insert(0, parse_quote!('de: #(#borrowed)+*));
+    let (impl_generics, _, where_clause) = generics.split_for_impl();
+
+    // plain structs are just their fields, so they can encode to nothing;
+    // everything else starts with a count or discriminant
+    let min_size = match &input.data {
+        Data::Struct(data) if !tagged => {
+            let types = data.fields.iter().map(|field| &field.ty);
+            quote!(const MIN_SIZE: usize = 0 #(+ <#types as #krate::DeSerialize<'de>>::MIN_SIZE)*;)
+        }
+        _ => quote!(),
+    };
+
+    let body = match &input.data {
+        Data::Struct(data) if tagged => {
+            let fields = tagged_fields(&data.fields)?;
+            let slots: Vec<_> = (0..fields.len())
+                .map(|i| format_ident!("__slot{}", i))
+                .collect();
+            let arms = fields.iter().zip(&slots).map(|(field, slot)| {
+                let id = field.id;
+                quote!(#id => #slot = ::core::option::Option::Some(field.decode()?),)
+            });
+            let values = fields.iter().zip(&slots).map(|(field, slot)| {
+                let member = &field.member;
+                let default = &field.default;
+                quote! {
+                    #member: match #slot {
+                        ::core::option::Option::Some(value) => value,
+                        ::core::option::Option::None => #default,
+                    }
+                }
+            });
+            quote! {
+                #(let mut #slots = ::core::option::Option::None;)*
+                let mut fields = #krate::TaggedFields::read(reader)?;
+                while let ::core::option::Option::Some(field) = fields.next_field(reader)? {
+                    match field.id() {
+                        #(#arms)*
+                        _ => {}
+                    }
+                }
+                ::core::result::Result::Ok(Self { #(#values),* })
+            }
+        }
+        Data::Struct(data) => {
+            let fields = construct(&data.fields, &krate);
+            quote!(::core::result::Result::Ok(Self #fields))
+        }
+        Data::Enum(data) => {
+            let (consts, discriminants) = discriminant_consts(data);
+            let arms = data
+                .variants
+                .iter()
+                .zip(&discriminants)
+                .map(|(variant, discriminant)| {
+                    let ident = &variant.ident;
+                    let fields = construct(&variant.fields, &krate);
+                    quote! {
+                        #discriminant => ::core::result::Result::Ok(Self::#ident #fields),
+                    }
+                });
+            quote! {
+                #consts
+                let start = #krate::Reader::position(&*reader);
+                let discriminant =
+                    <#krate::Discriminant as #krate::DeSerialize>::deserialize(reader)?;
+                match discriminant.0 {
+                    #(#arms)*
+                    _ => ::core::result::Result::Err(discriminant.invalid(start)),
+                }
+            }
+        }
+        Data::Union(data) => {
+            return Err(syn::Error::new_spanned(
+                data.union_token,
+                "DeSerialize cannot be derived for unions",
+            ))
+        }
+    };
+
+    Ok(quote! {
+        impl #impl_generics #krate::DeSerialize<'de> for #name #ty_generics #where_clause {
+            #min_size
+
+            #[allow(unused_variables)]
+            fn deserialize<__R: #krate::Reader<'de> + ?Sized>(
+                reader: &mut __R,
+            ) -> ::core::result::Result<Self, #krate::DeserializeError> {
+                #body
+            }
+        }
+    })
+}
+
+fn describe_fields(fields: &Fields, krate: &Path) -> Vec<TokenStream2> {
+    fields
+        .iter()
+        .enumerate()
+        .map(|(i, field)| {
+            let name = match &field.ident {
+                Some(ident) => ident.to_string(),
+                None => i.to_string(),
+            };
+            let ty = &field.ty;
+            quote!(#krate::Field::new(#name, <#ty as #krate::Describe>::schema()))
+        })
+        .collect()
+}
+
+fn expand_describe(input: DeriveInput) -> syn::Result<TokenStream2> {
+    let Container { krate, tagged } = container_attrs(&input)?;
+    if !tagged {
+        reject_field_attrs(&input)?;
+    }
+    let name = &input.ident;
+    let name_str = name.to_string();
+    let generics = add_bounds(input.generics.clone(), &parse_quote!(#krate::Describe));
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+
+    let body = match &input.data {
+        Data::Struct(data) if tagged => {
+            let ids = tagged_fields(&data.fields)?
+                .into_iter()
+                .map(|field| field.id);
+            let fields = describe_fields(&data.fields, &krate);
+            quote! {
+                #krate::Schema::Tagged {
+                    name: #name_str.into(),
+                    fields: ::std::vec![#((#ids, #fields)),*],
+                }
+            }
+        }
+        Data::Struct(data) => {
+            let fields = describe_fields(&data.fields, &krate);
+            quote! {
+                #krate::Schema::Struct {
+                    name: #name_str.into(),
+                    fields: ::std::vec![#(#fields),*],
+                }
+            }
+        }
+        Data::Enum(data) => {
+            let (consts, discriminants) = discriminant_consts(data);
+            let variants =
+                data.variants
+                    .iter()
+                    .zip(&discriminants)
+                    .map(|(variant, discriminant)| {
+                        let variant_name = variant.ident.to_string();
+                        let fields = describe_fields(&variant.fields, &krate);
+                        quote! {
+                            #krate::Variant::new(
+                                #variant_name,
+                                #discriminant,
+                                ::std::vec![#(#fields),*],
+                            )
+                        }
+                    });
+            quote! {
+                #consts
+                #krate::Schema::Enum {
+                    name: #name_str.into(),
+                    variants: ::std::vec![#(#variants),*],
+                }
+            }
+        }
+        Data::Union(data) => {
+            return Err(syn::Error::new_spanned(
+                data.union_token,
+                "Describe cannot be derived for unions",
+            ))
+        }
+    };
+
+    Ok(quote! {
+        impl #impl_generics #krate::Describe for #name #ty_generics #where_clause {
+            fn schema() -> #krate::Schema {
+                #body
+            }
+        }
+    })
+}
@@ -0,0 +1,504 @@
+//! Inspects files written with `SBI::write_to_path` without writing any Rust.
+//!
+//! Values are decoded against a schema file, which holds a `Schema` written
+//! by `SBI::write_to_path` with the default config, for example with
+//!
+//! ```ignore
+//! let mut sbi = SBI::new();
+//! sbi.serialize(SaveGame::schema());
+//! sbi.write_to_path("save.schema")?;
+//! ```
+
+use std::{
+    collections::HashMap,
+    env,
+    error::Error,
+    fs,
+    io::{self, Read as _, Write as _},
+    process::ExitCode,
+};
+
+use sbs_api_internal::{ByteOrder, Config, Header, IntEncoding, Schema, SBI};
+
+const USAGE: &str = "\
+Usage: sbs-inspect <command> [options] <file>...
+
+Commands:
+  header <file>         show the header at the start of the file
+  hex <file>            hex dump, annotated with the values when --schema is given
+  decode <file>         list the values in the file, needs --schema
+  diff <file> <file>    list the values that differ between two files, needs --schema
+
+Options:
+  --schema <file>       file holding the `Schema` of the data
+  --header              the files start with a header, with any magic
+  --magic <magic>       the files start with a header with this 4 byte magic
+  --varint              integers are varints
+  --little, --native    byte order of numbers, big endian by default
+  --track-refs          Rc/Arc sharing is tracked
+  --lossy-utf8          replace invalid UTF-8 in strings instead of failing
+
+The encoding options are only used for files without a header.
+
+Exits with 1 if a file doesn't match the schema or the files differ, and
+with 2 on any other error.
+";
+
+/// Bytes shown on each row of a hex dump.
+const ROW: usize = 16;
+
+struct Options {
+    command: String,
+    files: Vec<String>,
+    schema: Option<String>,
+    header: bool,
+    magic: Option<[u8; 4]>,
+    config: Config,
+}
+
+fn main() -> ExitCode {
+    let args: Vec<_> = env::args().skip(1).collect();
+    if args.iter().any(|arg| arg == "-h" || arg == "--help") {
+        print!("{USAGE}");
+        return ExitCode::SUCCESS;
+    }
+
+    let options = match parse_args(args.into_iter()) {
+        Ok(options) => options,
+        Err(message) => {
+            eprintln!("sbs-inspect: {message}\n\n{USAGE}");
+            return ExitCode::from(2);
+        }
+    };
+
+    let mut out = io::stdout().lock();
+    match run(&options, &mut out) {
+        Ok(true) => ExitCode::SUCCESS,
+        Ok(false) => ExitCode::from(1),
+        Err(err) => {
+            let _ = out.flush();
+            eprintln!("sbs-inspect: {err}");
+            ExitCode::from(2)
+        }
+    }
+}
+
+fn parse_args(mut args: impl Iterator<Item = String>) -> Result<Options, String> {
+    let mut options = Options {
+        command: String::new(),
+        files: Vec::new(),
+        schema: None,
+        header: false,
+        magic: None,
+        config: Config::default(),
+    };
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--schema" => options.schema = Some(args.next().ok_or("--schema needs a file")?),
+            "--header" => options.header = true,
+            "--magic" => {
+                let magic = args.next().ok_or("--magic needs a value")?;
+                let magic = magic.as_bytes().try_into();
+                options.magic = Some(magic.map_err(|_| "--magic must be 4 bytes long")?);
+            }
+            "--varint" => options.config.int_encoding = IntEncoding::Varint,
+            "--little" => options.config.byte_order = ByteOrder::Little,
+            "--native" => options.config.byte_order = ByteOrder::Native,
+            "--track-refs" => options.config.track_refs = true,
+            "--lossy-utf8" => options.config.lossy_utf8 = true,
+            _ if arg.starts_with("--") => return Err(format!("unknown option {arg}")),
+            _ if options.command.is_empty() => options.command = arg,
+            _ => options.files.push(arg),
+        }
+    }
+
+    let files = match options.command.as_str() {
+        "header" | "hex" | "decode" => 1,
+        "diff" => 2,
+        "" => return Err("missing command".into()),
+        command => return Err(format!("unknown command {command}")),
+    };
+    if options.files.len() != files {
+        return Err(format!("{} takes {files} file(s)", options.command));
+    }
+
+    Ok(options)
+}
+
+/// Runs the command, returning whether the files matched the schema and,
+/// for `diff`, each other.
+fn run(options: &Options, out: &mut dyn io::Write) -> Result<bool, Box<dyn Error>> {
+    let schema = match &options.schema {
+        Some(path) => Some(SBI::from_path(path)?.deserialize::<Schema>()?),
+        None => None,
+    };
+    let needs_schema = || -> Result<&Schema, Box<dyn Error>> {
+        let schema = schema.as_ref();
+        schema.ok_or_else(|| format!("{} needs --schema", options.command).into())
+    };
+
+    match options.command.as_str() {
+        "header" => {
+            let magic = match options.magic {
+                Some(magic) => magic,
+                None => read_magic(&options.files[0])?,
+            };
+            let sbi = load(&options.files[0], Some(magic), options.config)?;
+            header(&sbi, out)?;
+            Ok(true)
+        }
+        "hex" => {
+            let sbi = load_options(&options.files[0], options)?;
+            match &schema {
+                Some(schema) => annotated_hex(schema, &sbi, out),
+                None => {
+                    hex(&sbi.data, out)?;
+                    Ok(true)
+                }
+            }
+        }
+        "decode" => {
+            let sbi = load_options(&options.files[0], options)?;
+            let dump = needs_schema()?.dump(&sbi);
+            write!(out, "{dump}")?;
+            Ok(dump.mismatch().is_none())
+        }
+        "diff" => {
+            let schema = needs_schema()?;
+            let a = load_options(&options.files[0], options)?;
+            let b = load_options(&options.files[1], options)?;
+            diff(
+                schema,
+                [&options.files[0], &options.files[1]],
+                [&a, &b],
+                out,
+            )
+        }
+        _ => unreachable!("checked by parse_args"),
+    }
+}
+
+fn read_magic(path: &str) -> io::Result<[u8; 4]> {
+    let mut magic = Vec::with_capacity(4);
+    fs::File::open(path)?.take(4).read_to_end(&mut magic)?;
+
+    // a short file is reported by `Header::decode`
+    magic.resize(4, 0);
+    Ok(magic.try_into().unwrap())
+}
+
+/// Loads the file, accepting a header with the given magic and any version.
+fn load(path: &str, magic: Option<[u8; 4]>, config: Config) -> Result<SBI, Box<dyn Error>> {
+    let header = magic.map(|magic| Header::new(magic, u16::MAX));
+    SBI::from_path_with_config(path, Config { header, ..config })
+        .map_err(|err| format!("{path}: {err}").into())
+}
+
+fn load_options(path: &str, options: &Options) -> Result<SBI, Box<dyn Error>> {
+    let magic = match options.magic {
+        Some(magic) => Some(magic),
+        None if options.header => Some(read_magic(path)?),
+        None => None,
+    };
+    load(path, magic, options.config)
+}
+
+fn header(sbi: &SBI, out: &mut dyn io::Write) -> io::Result<()> {
+    let config = &sbi.config;
+    let header = config.header.expect("loaded with a header");
+
+    writeln!(
+        out,
+        "magic       {} ({:02x?})",
+        printable(&header.magic),
+        header.magic
+    )?;
+    writeln!(out, "version     {}", header.version)?;
+    writeln!(out, "byte order  {:?}", config.byte_order)?;
+    writeln!(out, "integers    {:?}", config.int_encoding)?;
+    writeln!(out, "track refs  {}", config.track_refs)?;
+    writeln!(out, "body        {} bytes", sbi.data.len())
+}
+
+/// Summary of the header for `diff`, if the file has one.
+fn header_line(config: &Config) -> Option<String> {
+    config.header.map(|header| {
+        format!(
+            "header: {} v{}, {:?} endian, {:?} integers, track refs {}",
+            printable(&header.magic),
+            header.version,
+            config.byte_order,
+            config.int_encoding,
+            config.track_refs,
+        )
+    })
+}
+
+fn printable(bytes: &[u8]) -> String {
+    let text = |byte: &u8| {
+        if byte.is_ascii_graphic() {
+            *byte as char
+        } else {
+            '.'
+        }
+    };
+    bytes.iter().map(text).collect()
+}
+
+fn hex(data: &[u8], out: &mut dyn io::Write) -> io::Result<()> {
+    for (row, bytes) in data.chunks(ROW).enumerate() {
+        writeln!(
+            out,
+            "{:08x}  {:<width$}  |{}|",
+            row * ROW,
+            hex_row(bytes),
+            printable(bytes),
+            width = ROW * 3 - 1
+        )?;
+    }
+    Ok(())
+}
+
+fn hex_row(bytes: &[u8]) -> String {
+    let hex: Vec<_> = bytes.iter().map(|byte| format!("{byte:02x}")).collect();
+    hex.join(" ")
+}
+
+/// Hex dump with each value of the dump next to the bytes it was read from.
+fn annotated_hex(
+    schema: &Schema,
+    sbi: &SBI,
+    out: &mut dyn io::Write,
+) -> Result<bool, Box<dyn Error>> {
+    let dump = schema.dump(sbi);
+    let entries = dump.entries();
+    let width = ROW * 3 - 1;
+
+    let mut shown = 0;
+    for (i, entry) in entries.iter().enumerate() {
+        // bytes up to the next value belong to this one, like a length
+        // prefix or discriminant, or the whole value for scalars
+        let start = entry.offset.clamp(shown, sbi.data.len());
+        let end = match entries.get(i + 1) {
+            Some(next) => next.offset.clamp(start, sbi.data.len()),
+            None => sbi.data.len(),
+        };
+
+        let mut rows = sbi.data[start..end].chunks(ROW);
+        let first = rows.next().unwrap_or_default();
+        writeln!(out, "{start:08x}  {:<width$}  {entry}", hex_row(first))?;
+        for (row, bytes) in rows.enumerate() {
+            writeln!(out, "{:08x}  {}", start + (row + 1) * ROW, hex_row(bytes))?;
+        }
+        shown = end;
+    }
+
+    Ok(dump.mismatch().is_none())
+}
+
+/// Lists the values that were added, removed or changed between the files,
+/// along with the structs and collections they're in.
+fn diff(
+    schema: &Schema,
+    paths: [&str; 2],
+    sbis: [&SBI; 2],
+    out: &mut dyn io::Write,
+) -> Result<bool, Box<dyn Error>> {
+    let dumps = sbis.map(|sbi| schema.dump(sbi));
+    let listings: Vec<Vec<Line>> = dumps
+        .iter()
+        .zip(sbis)
+        .map(|(dump, sbi)| {
+            let header = header_line(&sbi.config).map(|text| Line {
+                offset: None,
+                depth: 1,
+                text,
+            });
+            let lines = dump.entries().iter().map(|entry| Line {
+                offset: Some(entry.offset),
+                depth: entry.depth,
+                text: entry.to_string(),
+            });
+            header.into_iter().chain(lines).collect()
+        })
+        .collect();
+
+    let matched = dumps.iter().all(|dump| dump.mismatch().is_none());
+    let changes = diff_lines(&listings[0], &listings[1]);
+    if changes
+        .iter()
+        .all(|change| matches!(change, Change::Same(..)))
+    {
+        return Ok(matched);
+    }
+
+    writeln!(out, "--- {}\n+++ {}", paths[0], paths[1])?;
+
+    // unchanged lines enclosing the current one, with whether each has been
+    // written yet
+    let mut enclosing: Vec<(&Line, bool)> = Vec::new();
+    for change in changes {
+        let (sign, line) = match change {
+            Change::Same(line) => {
+                enclosing.retain(|(other, _)| other.depth < line.depth);
+                enclosing.push((line, false));
+                continue;
+            }
+            Change::Removed(line) => ('-', line),
+            Change::Added(line) => ('+', line),
+        };
+
+        enclosing.retain(|(other, _)| other.depth < line.depth);
+        for (line, written) in &mut enclosing {
+            if !*written {
+                write_line(' ', line, out)?;
+                *written = true;
+            }
+        }
+        write_line(sign, line, out)?;
+        enclosing.push((line, true));
+    }
+
+    Ok(false)
+}
+
+/// A line of the listings compared by `diff`.
+struct Line {
+    /// `None` for the header summary, which sits next to the root value.
+    offset: Option<usize>,
+    depth: usize,
+    text: String,
+}
+
+fn write_line(sign: char, line: &Line, out: &mut dyn io::Write) -> io::Result<()> {
+    let text = &line.text;
+    match line.offset {
+        Some(offset) => writeln!(out, "{sign} {offset:08x}  {text}"),
+        None => writeln!(out, "{sign} {:8}  {text}", ""),
+    }
+}
+
+enum Change<'a> {
+    Same(&'a Line),
+    Removed(&'a Line),
+    Added(&'a Line),
+}
+
+/// Most differences `diff_lines` aligns; the alignment takes memory
+/// quadratic in the number of differences, so listings that differ in more
+/// places than this are compared coarsely instead.
+const MAX_EDITS: usize = 2048;
+
+/// Diffs the listings by their text, ignoring offsets, which shift whenever
+/// anything before them changes size. Uses Myers' O(ND) algorithm on the
+/// lines between the common prefix and suffix, or, beyond `MAX_EDITS`, lists
+/// all of those lines as removed and added.
+fn diff_lines<'a>(a: &'a [Line], b: &'a [Line]) -> Vec<Change<'a>> {
+    let prefix = a
+        .iter()
+        .zip(b)
+        .take_while(|(x, y)| x.text == y.text)
+        .count();
+    let suffix = a[prefix..]
+        .iter()
+        .rev()
+        .zip(b[prefix..].iter().rev())
+        .take_while(|(x, y)| x.text == y.text)
+        .count();
+    let (middle_a, middle_b) = (&a[prefix..a.len() - suffix], &b[prefix..b.len() - suffix]);
+
+    let mut changes: Vec<_> = a[..prefix].iter().map(Change::Same).collect();
+    match myers(middle_a, middle_b) {
+        Some(middle) => changes.extend(middle),
+        None => {
+            changes.extend(middle_a.iter().map(Change::Removed));
+            changes.extend(middle_b.iter().map(Change::Added));
+        }
+    }
+    changes.extend(b[b.len() - suffix..].iter().map(Change::Same));
+    changes
+}
+
+/// Shortest edit script from `a` to `b`, or `None` if it takes more than
+/// `MAX_EDITS` edits.
+fn myers<'a>(a: &'a [Line], b: &'a [Line]) -> Option<Vec<Change<'a>>> {
+    // compare small ids instead of the text
+    let mut ids = HashMap::new();
+    let mut id = |line: &'a Line| {
+        let next = ids.len();
+        *ids.entry(line.text.as_str()).or_insert(next)
+    };
+    let a_ids: Vec<_> = a.iter().map(&mut id).collect();
+    let b_ids: Vec<_> = b.iter().map(&mut id).collect();
+
+    let (n, m) = (a.len() as isize, b.len() as isize);
+    let max = (a.len() + b.len()).min(MAX_EDITS) as isize;
+
+    // `v[k]` is how far along `a` the furthest path on diagonal `k = x - y`
+    // gets; `trace[d]` keeps diagonals `-d..=d` of it after `d` edits
+    let mut v = vec![0isize; 2 * max as usize + 3];
+    let at = |k: isize| (k + max + 1) as usize;
+    let mut trace: Vec<Vec<isize>> = Vec::new();
+
+    let mut edits = None;
+    'search: for d in 0..=max {
+        for k in (-d..=d).step_by(2) {
+            let mut x = if k == -d || (k != d && v[at(k - 1)] < v[at(k + 1)]) {
+                v[at(k + 1)]
+            } else {
+                v[at(k - 1)] + 1
+            };
+            let mut y = x - k;
+            while x < n && y < m && a_ids[x as usize] == b_ids[y as usize] {
+                (x, y) = (x + 1, y + 1);
+            }
+            v[at(k)] = x;
+
+            if x >= n && y >= m {
+                edits = Some(d);
+                break 'search;
+            }
+        }
+        trace.push(v[at(-d)..=at(d)].to_vec());
+    }
+    let edits = edits?;
+
+    // walk back from the end, one edit and the run of equal lines after it
+    // at a time
+    let mut changes = Vec::new();
+    let (mut x, mut y) = (n, m);
+    for d in (1..=edits).rev() {
+        let previous = &trace[d as usize - 1];
+        let furthest = |k: isize| previous[(k + d - 1) as usize];
+
+        let k = x - y;
+        let inserted = k == -d || (k != d && furthest(k - 1) < furthest(k + 1));
+        let previous_k = if inserted { k + 1 } else { k - 1 };
+        let previous_x = furthest(previous_k);
+        let previous_y = previous_x - previous_k;
+        let (start_x, start_y) = if inserted {
+            (previous_x, previous_y + 1)
+        } else {
+            (previous_x + 1, previous_y)
+        };
+
+        while x > start_x && y > start_y {
+            (x, y) = (x - 1, y - 1);
+            changes.push(Change::Same(&b[y as usize]));
+        }
+        changes.push(if inserted {
+            Change::Added(&b[previous_y as usize])
+        } else {
+            Change::Removed(&a[previous_x as usize])
+        });
+        (x, y) = (previous_x, previous_y);
+    }
+    while x > 0 && y > 0 {
+        (x, y) = (x - 1, y - 1);
+        changes.push(Change::Same(&b[y as usize]));
+    }
+
+    changes.reverse();
+    Some(changes)
+}
@@ -0,0 +1,173 @@
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap, HashSet, VecDeque},
+    hash::{BuildHasher, Hash},
+};
+
+use crate::{capacity_hint, Config, DeSerialize, DeserializeError, Reader, Serialize, Writer, SBI};
+
+// All collections use the same layout as `Vec<T>`: a `u64` length prefix
+// followed by the elements, with map entries written as `(key, value)`.
+
+fn serialize_seq<'a, T, I, W>(len: usize, items: I, writer: &mut W)
+where
+    T: Serialize + 'a,
+    W: Writer + ?Sized,
+    I: Iterator<Item = &'a T>,
+{
+    (len as u64).serialize(writer);
+
+    for item in items {
+        item.serialize(writer)
+    }
+}
+
+/// Writes the entries of a hash collection. When `Config::canonical` is set
+/// the entries are sorted by the encoded bytes of their keys, so equal
+/// collections always produce identical output whatever their iteration
+/// order.
+fn serialize_unordered<'a, K, V, I, W>(len: usize, entries: I, writer: &mut W)
+where
+    W: Writer + ?Sized,
+    K: Serialize + ?Sized + 'a,
+    V: Serialize + ?Sized + 'a,
+    I: Iterator<Item = (&'a K, &'a V)>,
+{
+    (len as u64).serialize(writer);
+
+    if !writer.config().canonical {
+        for (key, value) in entries {
+            key.serialize(writer);
+            value.serialize(writer);
+        }
+        return;
+    }
+
+    // keys are encoded without reference tracking to find the order, so the
+    // order only depends on their contents; the entries are then written in
+    // that order, so shared ids are still handed out in the order they are
+    // read back in
+    let mut scratch = SBI::with_config(Config {
+        track_refs: false,
+        ..*writer.config()
+    });
+    let mut keyed = Vec::with_capacity(len);
+    for (key, value) in entries {
+        let start = scratch.data.len();
+        key.serialize(&mut scratch);
+        keyed.push((start..scratch.data.len(), key, value));
+    }
+
+    keyed.sort_by(|a, b| scratch.data[a.0.clone()].cmp(&scratch.data[b.0.clone()]));
+    for (_, key, value) in keyed {
+        key.serialize(writer);
+        value.serialize(writer);
+    }
+}
+
+impl<T: Serialize> Serialize for VecDeque<T> {
+    fn serialize<W: Writer + ?Sized>(&self, writer: &mut W) {
+        serialize_seq(self.len(), self.iter(), writer)
+    }
+}
+
+impl<'de, T: DeSerialize<'de>> DeSerialize<'de> for VecDeque<T> {
+    fn deserialize<R: Reader<'de> + ?Sized>(reader: &mut R) -> Result<Self, DeserializeError> {
+        Vec::<T>::deserialize(reader).map(VecDeque::from)
+    }
+}
+
+impl<T: Serialize> Serialize for BTreeSet<T> {
+    fn serialize<W: Writer + ?Sized>(&self, writer: &mut W) {
+        serialize_seq(self.len(), self.iter(), writer)
+    }
+}
+
+impl<'de, T: DeSerialize<'de> + Ord> DeSerialize<'de> for BTreeSet<T> {
+    fn deserialize<R: Reader<'de> + ?Sized>(reader: &mut R) -> Result<Self, DeserializeError> {
+        let len = reader.read_len(T::MIN_SIZE)?;
+
+        let mut ret = BTreeSet::new();
+        for _ in 0..len {
+            ret.insert(T::deserialize(reader)?);
+        }
+
+        Ok(ret)
+    }
+}
+
+impl<T: Serialize, S> Serialize for HashSet<T, S> {
+    fn serialize<W: Writer + ?Sized>(&self, writer: &mut W) {
+        serialize_unordered(self.len(), self.iter().map(|item| (item, &())), writer)
+    }
+}
+
+impl<'de, T, S> DeSerialize<'de> for HashSet<T, S>
+where
+    T: DeSerialize<'de> + Eq + Hash,
+    S: BuildHasher + Default,
+{
+    fn deserialize<R: Reader<'de> + ?Sized>(reader: &mut R) -> Result<Self, DeserializeError> {
+        let len = reader.read_len(T::MIN_SIZE)?;
+
+        let mut ret = HashSet::with_capacity_and_hasher(capacity_hint::<T>(len), S::default());
+        for _ in 0..len {
+            ret.insert(T::deserialize(reader)?);
+        }
+
+        Ok(ret)
+    }
+}
+
+impl<K: Serialize, V: Serialize> Serialize for BTreeMap<K, V> {
+    fn serialize<W: Writer + ?Sized>(&self, writer: &mut W) {
+        (self.len() as u64).serialize(writer);
+
+        for (key, value) in self.iter() {
+            key.serialize(writer);
+            value.serialize(writer);
+        }
+    }
+}
+
+impl<'de, K, V> DeSerialize<'de> for BTreeMap<K, V>
+where
+    K: DeSerialize<'de> + Ord,
+    V: DeSerialize<'de>,
+{
+    fn deserialize<R: Reader<'de> + ?Sized>(reader: &mut R) -> Result<Self, DeserializeError> {
+        let len = reader.read_len(K::MIN_SIZE.saturating_add(V::MIN_SIZE))?;
+
+        let mut ret = BTreeMap::new();
+        for _ in 0..len {
+            let key = K::deserialize(reader)?;
+            ret.insert(key, V::deserialize(reader)?);
+        }
+
+        Ok(ret)
+    }
+}
+
+impl<K: Serialize, V: Serialize, S> Serialize for HashMap<K, V, S> {
+    fn serialize<W: Writer + ?Sized>(&self, writer: &mut W) {
+        serialize_unordered(self.len(), self.iter(), writer)
+    }
+}
+
+impl<'de, K, V, S> DeSerialize<'de> for HashMap<K, V, S>
+where
+    K: DeSerialize<'de> + Eq + Hash,
+    V: DeSerialize<'de>,
+    S: BuildHasher + Default,
+{
+    fn deserialize<R: Reader<'de> + ?Sized>(reader: &mut R) -> Result<Self, DeserializeError> {
+        let len = reader.read_len(K::MIN_SIZE.saturating_add(V::MIN_SIZE))?;
+
+        let mut ret = HashMap::with_capacity_and_hasher(capacity_hint::<(K, V)>(len), S::default());
+        for _ in 0..len {
+            let key = K::deserialize(reader)?;
+            ret.insert(key, V::deserialize(reader)?);
+        }
+
+        Ok(ret)
+    }
+}
@@ -0,0 +1,45 @@
+use crate::Header;
+
+/// Format options shared by serialization and deserialization.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Config {
+    /// Replace invalid UTF-8 in strings with U+FFFD instead of failing with
+    /// `DeserializeError::InvalidUtf8`.
+    pub lossy_utf8: bool,
+    /// How integers wider than a byte, including `Vec` and `String` length
+    /// prefixes, are written.
+    pub int_encoding: IntEncoding,
+    /// Byte order of fixed-width numbers.
+    pub byte_order: ByteOrder,
+    /// Write `HashMap` and `HashSet` entries sorted by their encoded keys so
+    /// the output doesn't depend on hash iteration order.
+    pub canonical: bool,
+    /// Write each `Rc`/`Arc` allocation only once per root and encode later
+    /// pointers to it as back-references, so sharing survives a round trip.
+    pub track_refs: bool,
+    /// Fail with `DeserializeError::TrailingBytes` when `SBI::deserialize` or
+    /// `ByteSource::deserialize` leaves part of the buffer unread.
+    pub reject_trailing_bytes: bool,
+    /// Header that `SBI::write_to_path` starts the file with and
+    /// `SBI::from_path_with_config` expects to find.
+    pub header: Option<Header>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum IntEncoding {
+    /// `size_of::<T>()` bytes in `Config::byte_order`.
+    #[default]
+    Fixed,
+    /// Unsigned LEB128, with signed integers zigzag encoded first.
+    Varint,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ByteOrder {
+    #[default]
+    Big,
+    Little,
+    /// Whatever the writing machine uses; only portable between hosts of the
+    /// same endianness.
+    Native,
+}
@@ -0,0 +1,43 @@
+use crate::{varint, DeSerialize, DeserializeError, Reader, Serialize, Writer};
+
+/// Tag written in front of an enum variant's payload.
+///
+/// Enums are encoded as the variant's discriminant followed by the variant's
+/// fields in declaration order. The discriminant is always an unsigned LEB128
+/// varint, whatever `Config::int_encoding` says, so enums with fewer than 128
+/// variants spend a single byte on the tag. Unit variants have no payload;
+/// tuple and struct variants serialize each field the same way a tuple or
+/// named struct would.
+///
+/// `#[derive(Serialize, DeSerialize)]` uses the variant index as the
+/// discriminant unless the variant declares an explicit one (`A = 4`), in
+/// which case later implicit variants count up from it as in Rust.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Discriminant(pub u64);
+
+impl Discriminant {
+    /// Error for a discriminant read at `offset` that matches no variant.
+    pub fn invalid(self, offset: usize) -> DeserializeError {
+        DeserializeError::InvalidDiscriminant {
+            offset,
+            discriminant: self.0,
+        }
+    }
+}
+
+impl Serialize for Discriminant {
+    fn serialize<W: Writer + ?Sized>(&self, writer: &mut W) {
+        varint::write_unsigned(self.0 as u128, writer)
+    }
+}
+
+impl<'de> DeSerialize<'de> for Discriminant {
+    fn deserialize<R: Reader<'de> + ?Sized>(reader: &mut R) -> Result<Self, DeserializeError> {
+        let start = reader.position();
+        let value = varint::read_unsigned(reader)?;
+
+        u64::try_from(value)
+            .map(Discriminant)
+            .map_err(|_| DeserializeError::InvalidVarint { offset: start })
+    }
+}
@@ -0,0 +1,433 @@
+use std::{borrow::Cow, fmt, fmt::Write as _};
+
+use crate::{
+    check_len, varint, Config, Cursor, DeSerialize, Describe, DeserializeError, Discriminant,
+    Field, Reader, Schema, TaggedFields, SBI,
+};
+
+/// First place where a buffer doesn't match a schema.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SchemaMismatch {
+    /// Where in the value the mismatch is, like `Save.inventory[2]`.
+    pub path: String,
+    pub error: DeserializeError,
+}
+
+impl SchemaMismatch {
+    pub fn offset(&self) -> usize {
+        self.error.offset()
+    }
+}
+
+impl fmt::Display for SchemaMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.path, self.error)
+    }
+}
+
+impl std::error::Error for SchemaMismatch {}
+
+/// Annotated listing of a buffer produced by `Schema::dump`.
+///
+/// Each line shows the offset of a value (in hex), its place in the tree,
+/// its type and, for scalars, its value. If the buffer doesn't match the
+/// schema, the listing stops at the value that failed with a line marked
+/// `<--` describing the error.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Dump {
+    entries: Vec<DumpEntry>,
+    text: String,
+    mismatch: Option<SchemaMismatch>,
+}
+
+impl Dump {
+    fn new(entries: Vec<DumpEntry>, mismatch: Option<SchemaMismatch>) -> Self {
+        let mut text = String::new();
+        for entry in &entries {
+            writeln!(text, "{:08x}  {entry}", entry.offset).unwrap();
+        }
+
+        Self {
+            entries,
+            text,
+            mismatch,
+        }
+    }
+
+    /// The lines of the listing.
+    pub fn entries(&self) -> &[DumpEntry] {
+        &self.entries
+    }
+
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    pub fn mismatch(&self) -> Option<&SchemaMismatch> {
+        self.mismatch.as_ref()
+    }
+}
+
+impl fmt::Display for Dump {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.text)
+    }
+}
+
+/// One line of a `Dump`. Names taken from the schema are escaped, so
+/// `label` and `text` never contain line breaks.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DumpEntry {
+    /// Offset of the value in the buffer.
+    pub offset: usize,
+    /// Nesting depth of the value, 1 for the root. The line about trailing
+    /// bytes after the root has depth 0.
+    pub depth: usize,
+    /// Field name, index or other place of the value in its parent, empty
+    /// at depth 0.
+    pub label: String,
+    /// Type and value, or the error when decoding failed.
+    pub text: String,
+}
+
+/// Formats the entry without its offset, indented by its depth.
+impl fmt::Display for DumpEntry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.label.is_empty() {
+            return f.write_str(&self.text);
+        }
+
+        let indent = self.depth.saturating_sub(1) * 2;
+        write!(f, "{:indent$}{}: {}", "", self.label, self.text)
+    }
+}
+
+impl Schema {
+    /// Checks that the buffer holds exactly one value matching the schema.
+    pub fn validate(&self, sbi: &SBI) -> Result<(), SchemaMismatch> {
+        self.validate_bytes(&sbi.data, sbi.config)
+    }
+
+    pub fn validate_bytes(&self, data: &[u8], config: Config) -> Result<(), SchemaMismatch> {
+        match Walker::run(self, data, config, None) {
+            Some(mismatch) => Err(mismatch),
+            None => Ok(()),
+        }
+    }
+
+    /// Walks the buffer like `validate`, listing every value on the way.
+    pub fn dump(&self, sbi: &SBI) -> Dump {
+        self.dump_bytes(&sbi.data, sbi.config)
+    }
+
+    pub fn dump_bytes(&self, data: &[u8], config: Config) -> Dump {
+        let mut entries = Vec::new();
+        let mismatch = Walker::run(self, data, config, Some(&mut entries));
+        Dump::new(entries, mismatch)
+    }
+}
+
+impl SBI {
+    /// Lists the buffer as it would be decoded into `T`, see `Schema::dump`.
+    pub fn dump<T: Describe + ?Sized>(&self) -> Dump {
+        T::schema().dump(self)
+    }
+}
+
+struct Walker<'a> {
+    /// Segments of the path to the value being walked; left in place when a
+    /// value fails to decode.
+    path: Vec<String>,
+    /// Nesting depth of the value being walked.
+    depth: usize,
+    /// Number of shared values seen, for checking back-references.
+    shared: u64,
+    /// Offset of the buffer being walked in the original input; tagged
+    /// fields are walked on their own.
+    base: usize,
+    /// Text to put in front of the next line, for wrappers that don't get a
+    /// line of their own.
+    note: Option<String>,
+    failed: bool,
+    out: Option<&'a mut Vec<DumpEntry>>,
+}
+
+impl<'a> Walker<'a> {
+    fn run(
+        schema: &Schema,
+        data: &[u8],
+        config: Config,
+        out: Option<&'a mut Vec<DumpEntry>>,
+    ) -> Option<SchemaMismatch> {
+        let mut walker = Walker {
+            path: Vec::new(),
+            depth: 0,
+            shared: 0,
+            base: 0,
+            note: None,
+            failed: false,
+            out,
+        };
+        let mut cursor = Cursor::with_config(data, config);
+
+        let root = match schema {
+            Schema::Struct { name, .. }
+            | Schema::Tagged { name, .. }
+            | Schema::Enum { name, .. } => escaped(name),
+            _ => schema.kind().to_string(),
+        };
+
+        let result = walker.child(root, schema, &mut cursor).and_then(|()| {
+            cursor.end().map_err(|err| {
+                if let Some(out) = &mut walker.out {
+                    out.push(DumpEntry {
+                        offset: err.offset(),
+                        depth: 0,
+                        label: String::new(),
+                        text: format!("<-- {err}"),
+                    });
+                }
+                err
+            })
+        });
+
+        result.err().map(|error| SchemaMismatch {
+            path: walker.path.concat(),
+            error,
+        })
+    }
+
+    /// Adds the line for the value at `offset`.
+    fn line(&mut self, offset: usize, text: fmt::Arguments<'_>) {
+        let note = self.note.take();
+        let Some(out) = &mut self.out else {
+            return;
+        };
+
+        let label = self
+            .path
+            .last()
+            .map_or("", |segment| segment.trim_start_matches('.'));
+        out.push(DumpEntry {
+            offset: self.base + offset,
+            depth: self.depth,
+            label: label.to_string(),
+            text: match note {
+                Some(note) => format!("{note} {text}"),
+                None => text.to_string(),
+            },
+        });
+    }
+
+    fn scalar<T: fmt::Debug>(&mut self, offset: usize, schema: &Schema, value: T) {
+        self.line(offset, format_args!("{} = {value:?}", schema.kind()))
+    }
+
+    fn child(
+        &mut self,
+        segment: impl Into<String>,
+        schema: &Schema,
+        cursor: &mut Cursor<'_>,
+    ) -> Result<(), DeserializeError> {
+        self.path.push(segment.into());
+        self.depth += 1;
+
+        if let Err(err) = self.walk(schema, cursor) {
+            if !self.failed {
+                self.failed = true;
+                let offset = err.offset();
+                self.line(offset, format_args!("{} <-- {err}", schema.kind()));
+            }
+            return Err(err);
+        }
+
+        self.depth -= 1;
+        self.path.pop();
+        Ok(())
+    }
+
+    fn walk(&mut self, schema: &Schema, cursor: &mut Cursor<'_>) -> Result<(), DeserializeError> {
+        let start = cursor.position();
+
+        macro_rules! scalar {
+            ($t:ty) => {{
+                let value = <$t>::deserialize(cursor)?;
+                self.scalar(start, schema, value);
+                Ok(())
+            }};
+        }
+
+        match schema {
+            Schema::Unit => {
+                self.line(start, format_args!("unit"));
+                Ok(())
+            }
+            Schema::Bool => scalar!(bool),
+            Schema::U8 => scalar!(u8),
+            Schema::U16 => scalar!(u16),
+            Schema::U32 => scalar!(u32),
+            Schema::U64 => scalar!(u64),
+            Schema::U128 => scalar!(u128),
+            Schema::I8 => scalar!(i8),
+            Schema::I16 => scalar!(i16),
+            Schema::I32 => scalar!(i32),
+            Schema::I64 => scalar!(i64),
+            Schema::I128 => scalar!(i128),
+            Schema::F32 => scalar!(f32),
+            Schema::F64 => scalar!(f64),
+            Schema::Char => scalar!(char),
+            Schema::String => scalar!(Cow<str>),
+            Schema::Option(item) => match u8::deserialize(cursor)? {
+                0 => {
+                    self.line(start, format_args!("option = None"));
+                    Ok(())
+                }
+                1 => {
+                    self.line(start, format_args!("option = Some"));
+                    self.child("?", item, cursor)
+                }
+                tag => Err(DeserializeError::InvalidTag {
+                    offset: start,
+                    tag: tag as u64,
+                }),
+            },
+            Schema::Seq(item) => {
+                let len = cursor.read_len(item.min_size())?;
+                self.line(start, format_args!("seq of {len}"));
+                for i in 0..len {
+                    self.child(format!("[{i}]"), item, cursor)?;
+                }
+                Ok(())
+            }
+            Schema::Map(key, value) => {
+                let len = cursor.read_len(key.min_size().max(value.min_size()))?;
+                self.line(start, format_args!("map of {len}"));
+                for i in 0..len {
+                    self.child(format!("[{i}].key"), key, cursor)?;
+                    self.child(format!("[{i}].value"), value, cursor)?;
+                }
+                Ok(())
+            }
+            Schema::Array(item, len) => {
+                // the length comes from the schema, but it still has to fit
+                let len = check_len(start, *len, item.min_size(), cursor.remaining())?;
+                self.line(start, format_args!("array of {len}"));
+                for i in 0..len {
+                    self.child(format!("[{i}]"), item, cursor)?;
+                }
+                Ok(())
+            }
+            Schema::Tuple(items) => {
+                self.line(start, format_args!("tuple"));
+                for (i, item) in items.iter().enumerate() {
+                    self.child(format!(".{i}"), item, cursor)?;
+                }
+                Ok(())
+            }
+            Schema::Struct { name, fields } => {
+                self.line(start, format_args!("struct {}", escaped(name)));
+                self.fields(fields, cursor)
+            }
+            Schema::Tagged { name, fields } => {
+                let mut tagged = TaggedFields::read(cursor)?;
+                self.line(start, format_args!("tagged {}", escaped(name)));
+
+                while let Some(field) = tagged.next_field(cursor)? {
+                    let Some((_, known)) = fields.iter().find(|(id, _)| *id == field.id()) else {
+                        self.path.push(format!("#{}", field.id()));
+                        self.depth += 1;
+                        let len = field.len();
+                        self.line(field.offset(), format_args!("unknown field, {len} bytes"));
+                        self.depth -= 1;
+                        self.path.pop();
+                        continue;
+                    };
+
+                    // fields are encoded on their own, see `tagged.rs`
+                    let outer = (self.shared, self.base);
+                    self.shared = 0;
+                    self.base += field.offset();
+                    let mut inner = field.cursor();
+                    let segment = format!(".{}", escaped(&known.name));
+                    let result = self.child(segment, &known.schema, &mut inner);
+                    (self.shared, self.base) = outer;
+                    result.map_err(|err| err.shifted(field.offset()))?;
+                }
+                Ok(())
+            }
+            Schema::Enum { name, variants } => {
+                let discriminant = Discriminant::deserialize(cursor)?;
+                let variant = variants
+                    .iter()
+                    .find(|variant| variant.discriminant == discriminant.0)
+                    .ok_or_else(|| discriminant.invalid(start))?;
+
+                let variant_name = escaped(&variant.name);
+                self.line(
+                    start,
+                    format_args!("enum {}::{variant_name}", escaped(name)),
+                );
+                self.path.push(format!("::{variant_name}"));
+                self.fields(&variant.fields, cursor)?;
+                self.path.pop();
+                Ok(())
+            }
+            Schema::Shared(item) => {
+                if !cursor.config().track_refs {
+                    return self.walk(item, cursor);
+                }
+
+                let tag = u64::try_from(varint::read_unsigned(cursor)?)
+                    .map_err(|_| DeserializeError::InvalidVarint { offset: start })?;
+                match tag {
+                    0 => {
+                        self.note = Some("shared".into());
+                        self.walk(item, cursor)?;
+                        self.shared += 1;
+                        Ok(())
+                    }
+                    tag if tag <= self.shared => {
+                        self.line(
+                            start,
+                            format_args!("shared = back-reference to #{}", tag - 1),
+                        );
+                        Ok(())
+                    }
+                    tag => Err(DeserializeError::InvalidReference {
+                        offset: start,
+                        id: tag - 1,
+                    }),
+                }
+            }
+            Schema::Versioned(version, item) => {
+                let found = u32::try_from(varint::read_unsigned(cursor)?)
+                    .map_err(|_| DeserializeError::InvalidVarint { offset: start })?;
+                if found != *version {
+                    return Err(DeserializeError::UnsupportedVersion {
+                        offset: start,
+                        version: found,
+                    });
+                }
+
+                self.note = Some(format!("v{found}"));
+                self.walk(item, cursor)
+            }
+        }
+    }
+
+    fn fields(
+        &mut self,
+        fields: &[Field],
+        cursor: &mut Cursor<'_>,
+    ) -> Result<(), DeserializeError> {
+        for field in fields {
+            self.child(format!(".{}", escaped(&field.name)), &field.schema, cursor)?;
+        }
+        Ok(())
+    }
+}
+
+/// Escapes a name from the schema so it can't break up the listing.
+fn escaped(name: &str) -> String {
+    name.escape_debug().to_string()
+}
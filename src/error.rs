@@ -0,0 +1,120 @@
+use std::{error, fmt};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DeserializeError {
+    UnexpectedEof { offset: usize, needed: usize },
+    LengthOverflow { offset: usize, len: u64 },
+    /// A length prefix asks for more than any buffer could hold, so unlike
+    /// `LengthOverflow` no amount of further data would satisfy it.
+    LengthTooLarge { offset: usize, len: u64 },
+    InvalidUtf8 { offset: usize },
+    InvalidChar { offset: usize, value: u32 },
+    InvalidTag { offset: usize, tag: u64 },
+    InvalidDiscriminant { offset: usize, discriminant: u64 },
+    InvalidVarint { offset: usize },
+    OutOfRange { offset: usize, ty: &'static str },
+    InvalidReference { offset: usize, id: u64 },
+    TrailingBytes { offset: usize, len: usize },
+    UnsupportedVersion { offset: usize, version: u32 },
+    /// A value is nested more than `limit` levels deep.
+    TooDeep { offset: usize, limit: usize },
+    Custom { offset: usize, message: String },
+}
+
+impl DeserializeError {
+    pub fn custom<M: fmt::Display>(offset: usize, message: M) -> Self {
+        Self::Custom {
+            offset,
+            message: message.to_string(),
+        }
+    }
+
+    /// Byte offset into the buffer at which decoding failed.
+    pub fn offset(&self) -> usize {
+        match self {
+            Self::UnexpectedEof { offset, .. }
+            | Self::LengthOverflow { offset, .. }
+            | Self::LengthTooLarge { offset, .. }
+            | Self::InvalidUtf8 { offset }
+            | Self::InvalidChar { offset, .. }
+            | Self::InvalidTag { offset, .. }
+            | Self::InvalidDiscriminant { offset, .. }
+            | Self::InvalidVarint { offset }
+            | Self::OutOfRange { offset, .. }
+            | Self::InvalidReference { offset, .. }
+            | Self::TrailingBytes { offset, .. }
+            | Self::UnsupportedVersion { offset, .. }
+            | Self::TooDeep { offset, .. }
+            | Self::Custom { offset, .. } => *offset,
+        }
+    }
+
+    /// Moves the reported offset `by` bytes further along, for errors from a
+    /// buffer that starts partway into a larger input.
+    pub(crate) fn shifted(mut self, by: usize) -> Self {
+        *self.offset_mut() += by;
+        self
+    }
+
+    fn offset_mut(&mut self) -> &mut usize {
+        match self {
+            Self::UnexpectedEof { offset, .. }
+            | Self::LengthOverflow { offset, .. }
+            | Self::LengthTooLarge { offset, .. }
+            | Self::InvalidUtf8 { offset }
+            | Self::InvalidChar { offset, .. }
+            | Self::InvalidTag { offset, .. }
+            | Self::InvalidDiscriminant { offset, .. }
+            | Self::InvalidVarint { offset }
+            | Self::OutOfRange { offset, .. }
+            | Self::InvalidReference { offset, .. }
+            | Self::TrailingBytes { offset, .. }
+            | Self::UnsupportedVersion { offset, .. }
+            | Self::TooDeep { offset, .. }
+            | Self::Custom { offset, .. } => offset,
+        }
+    }
+}
+
+impl fmt::Display for DeserializeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnexpectedEof { offset, needed } => {
+                write!(f, "unexpected end of data at offset {offset}, needed {needed} bytes")
+            }
+            Self::LengthOverflow { offset, len } => {
+                write!(f, "length prefix {len} at offset {offset} exceeds the remaining data")
+            }
+            Self::LengthTooLarge { offset, len } => {
+                write!(f, "length prefix {len} at offset {offset} can never be satisfied")
+            }
+            Self::InvalidUtf8 { offset } => write!(f, "invalid UTF-8 at offset {offset}"),
+            Self::InvalidChar { offset, value } => {
+                write!(f, "invalid char {value:#x} at offset {offset}")
+            }
+            Self::InvalidTag { offset, tag } => write!(f, "invalid tag {tag} at offset {offset}"),
+            Self::InvalidDiscriminant { offset, discriminant } => {
+                write!(f, "invalid enum discriminant {discriminant} at offset {offset}")
+            }
+            Self::InvalidVarint { offset } => write!(f, "malformed varint at offset {offset}"),
+            Self::OutOfRange { offset, ty } => {
+                write!(f, "value at offset {offset} does not fit in {ty}")
+            }
+            Self::InvalidReference { offset, id } => {
+                write!(f, "back-reference to unknown shared value {id} at offset {offset}")
+            }
+            Self::TrailingBytes { offset, len } => {
+                write!(f, "{len} trailing bytes after the value, starting at offset {offset}")
+            }
+            Self::UnsupportedVersion { offset, version } => {
+                write!(f, "unsupported version {version} at offset {offset}")
+            }
+            Self::TooDeep { offset, limit } => {
+                write!(f, "value at offset {offset} is nested more than {limit} levels deep")
+            }
+            Self::Custom { offset, message } => write!(f, "{message} at offset {offset}"),
+        }
+    }
+}
+
+impl error::Error for DeserializeError {}
@@ -0,0 +1,131 @@
+use std::{error, fmt};
+
+use crate::{ByteOrder, Config, IntEncoding};
+
+// A header is `LEN` bytes: the application's magic, its format version as a
+// big-endian `u16`, and one byte of flags recording the parts of the `Config`
+// that change the encoding:
+//
+//   bits 0-1  byte order (0 big, 1 little); `ByteOrder::Native` is written
+//             as the writing host's order, so other hosts can still read it
+//   bit 2     varint integers
+//   bit 3     reference tracking
+//
+// All other bits must be zero.
+
+const BYTE_ORDER_MASK: u8 = 0b0011;
+const VARINT: u8 = 0b0100;
+const TRACK_REFS: u8 = 0b1000;
+
+/// Identifies files written by `SBI::write_to_path`, see `Config::header`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Header {
+    /// Application specific bytes that every file must start with.
+    pub magic: [u8; 4],
+    /// Version of the application's data layout. Files with a newer version
+    /// than the one configured are rejected; older ones are loaded and
+    /// reported through `Config::header` so they can be migrated.
+    pub version: u16,
+}
+
+impl Header {
+    /// Number of bytes the header takes up at the start of a file.
+    pub const LEN: usize = 7;
+
+    pub fn new(magic: [u8; 4], version: u16) -> Self {
+        Self { magic, version }
+    }
+
+    /// Encodes this header along with the flags for `config`.
+    pub fn encode(&self, config: &Config) -> [u8; Self::LEN] {
+        let mut flags = match config.byte_order {
+            ByteOrder::Big => 0,
+            ByteOrder::Little => 1,
+            ByteOrder::Native if cfg!(target_endian = "big") => 0,
+            ByteOrder::Native => 1,
+        };
+        if config.int_encoding == IntEncoding::Varint {
+            flags |= VARINT;
+        }
+        if config.track_refs {
+            flags |= TRACK_REFS;
+        }
+
+        let [v0, v1] = self.version.to_be_bytes();
+        let [m0, m1, m2, m3] = self.magic;
+        [m0, m1, m2, m3, v0, v1, flags]
+    }
+
+    /// Checks the header at the start of `data` against `self`, returning
+    /// `config` updated with the header found and the encoding it records.
+    pub fn decode(&self, data: &[u8], config: Config) -> Result<Config, HeaderError> {
+        let header = data.get(..Self::LEN).ok_or(HeaderError::Truncated { len: data.len() })?;
+
+        let magic: [u8; 4] = header[..4].try_into().unwrap();
+        if magic != self.magic {
+            return Err(HeaderError::WrongMagic {
+                expected: self.magic,
+                found: magic,
+            });
+        }
+
+        let version = u16::from_be_bytes([header[4], header[5]]);
+        if version > self.version {
+            return Err(HeaderError::UnsupportedVersion {
+                version,
+                supported: self.version,
+            });
+        }
+
+        let flags = header[6];
+        let byte_order = match flags & BYTE_ORDER_MASK {
+            0 => ByteOrder::Big,
+            1 => ByteOrder::Little,
+            _ => return Err(HeaderError::InvalidFlags { flags }),
+        };
+        if flags & !(BYTE_ORDER_MASK | VARINT | TRACK_REFS) != 0 {
+            return Err(HeaderError::InvalidFlags { flags });
+        }
+
+        Ok(Config {
+            byte_order,
+            int_encoding: if flags & VARINT != 0 {
+                IntEncoding::Varint
+            } else {
+                IntEncoding::Fixed
+            },
+            track_refs: flags & TRACK_REFS != 0,
+            header: Some(Header::new(magic, version)),
+            ..config
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HeaderError {
+    /// The file is shorter than a header.
+    Truncated { len: usize },
+    /// The file doesn't start with the expected magic, so it was most likely
+    /// written by something else.
+    WrongMagic { expected: [u8; 4], found: [u8; 4] },
+    /// The file was written by a newer version of the application.
+    UnsupportedVersion { version: u16, supported: u16 },
+    InvalidFlags { flags: u8 },
+}
+
+impl fmt::Display for HeaderError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Truncated { len } => write!(f, "file of {len} bytes is too short for a header"),
+            Self::WrongMagic { expected, found } => {
+                write!(f, "wrong magic {found:02x?}, expected {expected:02x?}")
+            }
+            Self::UnsupportedVersion { version, supported } => {
+                write!(f, "format version {version} is newer than the supported version {supported}")
+            }
+            Self::InvalidFlags { flags } => write!(f, "invalid header flags {flags:#04x}"),
+        }
+    }
+}
+
+impl error::Error for HeaderError {}
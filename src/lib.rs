@@ -1,129 +1,619 @@
-use std::{error, fs, path, mem};
+use std::{borrow::Cow, cmp, error::Error, fs, io::{self, Write as _}, path, mem, ops, str};
+
+mod collections;
+mod config;
+mod discriminant;
+mod dump;
+mod error;
+mod header;
+mod shared;
+mod reader;
+mod schema;
+mod stream;
+mod tagged;
+mod varint;
+mod version;
+mod wrappers;
+mod writer;
+
+pub use config::{ByteOrder, Config, IntEncoding};
+pub use discriminant::Discriminant;
+pub use dump::{Dump, DumpEntry, SchemaMismatch};
+pub use error::DeserializeError;
+pub use header::{Header, HeaderError};
+pub use shared::{ReadRefTable, RefTable};
+pub use stream::{StreamError, StreamIter, StreamReader};
+pub use tagged::{TaggedField, TaggedFields};
+pub use reader::{ByteSource, Cursor, Values};
+pub use schema::{Describe, Field, Schema, Variant};
+pub use version::{Migrate, NoPrevious, Versioned};
+pub use writer::{HashWriter, IoWriter, SizeCounter};
+
+#[cfg(feature = "derive")]
+pub use sbs_api_internal_derive::{DeSerialize, Describe, Serialize};
+
+/// Upper bound on the bytes preallocated for a collection before any of its
+/// elements have been decoded, so a corrupt length prefix can't trigger a
+/// huge allocation.
+const MAX_PREALLOC_BYTES: usize = 1 << 20;
+
+/// Longest collection accepted when its elements encode to nothing at all,
+/// since such a length prefix can't be checked against the remaining data.
+const MAX_EMPTY_LEN: usize = 1 << 16;
+
+/// Checks a length found at `offset` against the `remaining` bytes, the way
+/// `Reader::read_len` does.
+pub(crate) fn check_len(
+    offset: usize,
+    len: u64,
+    min_element_size: usize,
+    remaining: usize,
+) -> Result<usize, DeserializeError> {
+    let bytes = usize::try_from(len)
+        .ok()
+        .and_then(|len| len.checked_mul(min_element_size))
+        .filter(|&bytes| bytes <= isize::MAX as usize);
+
+    let too_large = DeserializeError::LengthTooLarge { offset, len };
+
+    match bytes {
+        None => Err(too_large),
+        Some(0) if len > MAX_EMPTY_LEN as u64 => Err(too_large),
+        Some(bytes) if bytes > remaining => Err(DeserializeError::LengthOverflow { offset, len }),
+        Some(_) => Ok(len as usize),
+    }
+}
+
+/// How many `T`s to preallocate for a collection whose length prefix says
+/// `len`.
+pub(crate) fn capacity_hint<T>(len: usize) -> usize {
+    cmp::min(len, MAX_PREALLOC_BYTES / mem::size_of::<T>().max(1))
+}
 
 pub trait Serialize {
-    fn serialize(&self, sbi: &mut SBI);
+    fn serialize<W: Writer + ?Sized>(&self, writer: &mut W);
 } 
 
-pub trait DeSerialize {
-    fn deserialize(sbi: &mut SBI, offset: &mut usize) -> Result<Self, ()> where Self: Sized;
+/// Destination that `Serialize` impls write their bytes into.
+///
+/// Writing can't fail from the point of view of a `Serialize` impl; writers
+/// that can fail, like `IoWriter`, hold on to the first error and report it
+/// once the root value has been written.
+pub trait Writer {
+    fn write_bytes(&mut self, bytes: &[u8]);
+
+    fn config(&self) -> &Config;
+
+    fn refs(&mut self) -> &mut RefTable;
+}
+
+/// Source that `DeSerialize` impls read their bytes from.
+///
+/// A reader owns its position, so impls can only move forward through the
+/// data; `Cursor` is the reader over an in-memory buffer that `SBI` and
+/// `ByteSource` hand out.
+pub trait Reader<'de> {
+    fn config(&self) -> &Config;
+
+    /// Number of bytes consumed so far, which is what error offsets refer to.
+    fn position(&self) -> usize;
+
+    /// Number of bytes left to read.
+    fn remaining(&self) -> usize;
+
+    /// Returns the next `len` bytes without consuming them.
+    fn peek(&self, len: usize) -> Result<&'de [u8], DeserializeError>;
+
+    /// Consumes the next `len` bytes without looking at them.
+    fn skip(&mut self, len: usize) -> Result<(), DeserializeError>;
+
+    fn refs(&mut self) -> &mut ReadRefTable;
+
+    /// Consumes and returns the next `len` bytes.
+    fn read_bytes(&mut self, len: usize) -> Result<&'de [u8], DeserializeError> {
+        let bytes = self.peek(len)?;
+        self.skip(len)?;
+        Ok(bytes)
+    }
+
+    /// Reads a `u64` length prefix, rejecting it if it could not possibly be
+    /// satisfied by the rest of the data when every element takes at least
+    /// `min_element_size` bytes. Collections of elements that take no bytes
+    /// at all are limited to a fixed length instead.
+    ///
+    /// Lengths that need more bytes than any buffer can hold, or more empty
+    /// elements than the limit, fail with `LengthTooLarge`, since reading
+    /// more data can't fix them. The rest fail with `LengthOverflow`.
+    fn read_len(&mut self, min_element_size: usize) -> Result<usize, DeserializeError> {
+        let start = self.position();
+        let len = u64::deserialize(self)?;
+        check_len(start, len, min_element_size, self.remaining())
+    }
+}
+
+/// Types that can be decoded from a `Reader` such as `Cursor`.
+///
+/// `'de` is the lifetime of the buffer being read, which lets types such as
+/// `&'de str` and `&'de [u8]` borrow straight out of it instead of copying.
+pub trait DeSerialize<'de>: Sized {
+    /// Fewest bytes any value of the type encodes to, for checking length
+    /// prefixes against the rest of the data. Only types that can encode to
+    /// nothing at all, like `()`, may set it to 0.
+    const MIN_SIZE: usize = 1;
+
+    fn deserialize<R: Reader<'de> + ?Sized>(reader: &mut R) -> Result<Self, DeserializeError>;
 }
 
+/// Types that can be decoded without borrowing from the buffer.
+pub trait DeSerializeOwned: for<'de> DeSerialize<'de> {}
+
+impl<T> DeSerializeOwned for T where T: for<'de> DeSerialize<'de> {}
+
 pub struct SBI {
     pub data: Vec<u8>,
+    pub config: Config,
+    refs: RefTable,
+}
+
+impl Writer for SBI {
+    fn write_bytes(&mut self, bytes: &[u8]) {
+        self.data.extend_from_slice(bytes)
+    }
+
+    fn config(&self) -> &Config {
+        &self.config
+    }
+
+    fn refs(&mut self) -> &mut RefTable {
+        &mut self.refs
+    }
+}
+
+impl Default for SBI {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl SBI {
     pub fn new() -> Self {
+        Self::with_config(Config::default())
+    }
+
+    pub fn with_config(config: Config) -> Self {
         Self {
-            data: Vec::new()
+            data: Vec::new(),
+            config,
+            refs: RefTable::default(),
         }
     }
 
-    pub fn from_path<P: AsRef<path::Path>>(path: P) -> Result<Self, Box<dyn error::Error>> {
-        let file = fs::read(path)?;
+    pub fn from_path<P: AsRef<path::Path>>(path: P) -> Result<Self, Box<dyn Error>> {
+        Self::from_path_with_config(path, Config::default())
+    }
+
+    /// Loads a file written by `write_to_path`. When `config.header` is set
+    /// the file's header is checked against it and stripped, and the loaded
+    /// `SBI` takes the encoding and version recorded in the header.
+    pub fn from_path_with_config<P: AsRef<path::Path>>(path: P, config: Config) -> Result<Self, Box<dyn Error>> {
+        let mut data = fs::read(path)?;
+
+        let config = match config.header {
+            Some(header) => {
+                let config = header.decode(&data, config)?;
+                data.drain(..Header::LEN);
+                config
+            }
+            None => config,
+        };
 
-        Ok(Self {
-            data: file,
-        })
+        let mut sbi = Self::with_config(config);
+        sbi.data = data;
+
+        Ok(sbi)
     }
     
-    pub fn deserialize<T: DeSerialize>(&mut self) -> Result<T, ()> {
-        let mut offset = 0;
-        T::deserialize(self, &mut offset)
+    pub fn deserialize<'de, T: DeSerialize<'de>>(&'de self) -> Result<T, DeserializeError> {
+        let mut cursor = self.cursor();
+        let value = cursor.deserialize()?;
+
+        if self.config.reject_trailing_bytes {
+            cursor.end()?;
+        }
+
+        Ok(value)
+    }
+
+    /// Cursor over just `range` of the buffer, for decoding one region of a
+    /// file whose layout is known up front. Decoding only needs `&self`, so
+    /// any number of threads can each decode their own region of a shared
+    /// `SBI` (for example behind an `Arc`) at once. Error offsets count from
+    /// the start of the whole buffer.
+    ///
+    /// Panics if `range` is out of bounds.
+    pub fn region(&self, range: ops::Range<usize>) -> Cursor<'_> {
+        Cursor::region(&self.data, range, self.config)
+    }
+
+    /// Iterates over the root values stored back to back in the buffer, as
+    /// in an append-only log.
+    pub fn iter<'de, T: DeSerialize<'de>>(&'de self) -> Values<'de, T> {
+        Values::new(self.cursor())
+    }
+
+    /// Cursor over the buffer, for decoding several consecutive root values
+    /// and finding out how many bytes are left after them.
+    pub fn cursor(&self) -> Cursor<'_> {
+        Cursor::with_config(&self.data, self.config)
     }
 
     pub fn serialize<T: Serialize>(&mut self, root: T) {
+        self.refs.clear();
         root.serialize(self)
     }
 
-    pub fn write_to_path<P: AsRef<path::Path>>(&self, path: P) -> Result<(), Box<dyn error::Error>> {
-        fs::write(path, &self.data)?;
+    /// Writes the buffer to `path`, preceded by a header if `config.header`
+    /// is set.
+    pub fn write_to_path<P: AsRef<path::Path>>(&self, path: P) -> Result<(), Box<dyn Error>> {
+        match self.config.header {
+            Some(header) => {
+                let mut file = io::BufWriter::new(fs::File::create(path)?);
+                file.write_all(&header.encode(&self.config))?;
+                file.write_all(&self.data)?;
+                file.flush()?;
+            }
+            None => fs::write(path, &self.data)?,
+        }
+
         Ok(())
     }
 }
 
+macro_rules! write_fixed {
+    ($value:expr, $writer:expr) => {
+        match $writer.config().byte_order {
+            ByteOrder::Big => $writer.write_bytes(&$value.to_be_bytes()),
+            ByteOrder::Little => $writer.write_bytes(&$value.to_le_bytes()),
+            ByteOrder::Native => $writer.write_bytes(&$value.to_ne_bytes()),
+        }
+    };
+}
+
+macro_rules! read_fixed {
+    ($t:ty, $reader:expr) => {{
+        const SIZE: usize = mem::size_of::<$t>();
+
+        let data: [u8; SIZE] = $reader.read_bytes(SIZE)?.try_into().unwrap();
+        match $reader.config().byte_order {
+            ByteOrder::Big => <$t>::from_be_bytes(data),
+            ByteOrder::Little => <$t>::from_le_bytes(data),
+            ByteOrder::Native => <$t>::from_ne_bytes(data),
+        }
+    }};
+}
+
 macro_rules! impl_serde_for_num {
     ($($t:ty),*) => {
         $(
             impl Serialize for $t {
-                fn serialize(&self, sbi: &mut SBI) {
-                    sbi.data.extend_from_slice(&self.to_be_bytes());
+                fn serialize<W: Writer + ?Sized>(&self, writer: &mut W) {
+                    write_fixed!(self, writer)
                 }
             }
 
-            impl DeSerialize for $t {
-                fn deserialize(sbi: &mut SBI, offset: &mut usize) -> Result<Self, ()> {
-                    const SIZE: usize = mem::size_of::<$t>();
-                    let end_offset = *offset + SIZE;
-
-                    if end_offset > sbi.data.len() {
-                        Err(())
-                    } else {
-                        let data: [u8; SIZE] = (&sbi.data[*offset..end_offset]).try_into().unwrap();
-                        *offset = end_offset;
-                        Ok(<$t>::from_be_bytes(data))
-                    }
+            impl<'de> DeSerialize<'de> for $t {
+                fn deserialize<R: Reader<'de> + ?Sized>(reader: &mut R) -> Result<Self, DeserializeError> {
+                    Ok(read_fixed!($t, reader))
                 }
             }
         )*
     };
 }
 
+// single bytes and floats are always fixed width, whatever the config says
 impl_serde_for_num!(
     i8, 
-    i16, 
-    i32, 
-    i64, 
-    i128, 
-    isize, 
     u8, 
-    u16, 
-    u32, 
-    u64, 
-    u128, 
-    usize,
     f32,
     f64
 );
 
-impl<T: Serialize> Serialize for Vec<T> {
-    fn serialize(&self, sbi: &mut SBI) {
-        (self.len() as u64).serialize(sbi);
+macro_rules! impl_serde_for_int {
+    ($($t:ty => $write:ident, $read:ident);*) => {
+        $(
+            impl Serialize for $t {
+                fn serialize<W: Writer + ?Sized>(&self, writer: &mut W) {
+                    match writer.config().int_encoding {
+                        IntEncoding::Fixed => write_fixed!(self, writer),
+                        IntEncoding::Varint => varint::$write(*self as _, writer),
+                    }
+                }
+            }
+
+            impl<'de> DeSerialize<'de> for $t {
+                fn deserialize<R: Reader<'de> + ?Sized>(reader: &mut R) -> Result<Self, DeserializeError> {
+                    match reader.config().int_encoding {
+                        IntEncoding::Fixed => Ok(read_fixed!($t, reader)),
+                        IntEncoding::Varint => {
+                            let start = reader.position();
+                            let value = varint::$read(reader)?;
+
+                            <$t>::try_from(value).map_err(|_| DeserializeError::OutOfRange {
+                                offset: start,
+                                ty: stringify!($t),
+                            })
+                        }
+                    }
+                }
+            }
+        )*
+    };
+}
+
+impl_serde_for_int!(
+    i16 => write_signed, read_signed;
+    i32 => write_signed, read_signed;
+    i64 => write_signed, read_signed;
+    i128 => write_signed, read_signed;
+    u16 => write_unsigned, read_unsigned;
+    u32 => write_unsigned, read_unsigned;
+    u64 => write_unsigned, read_unsigned;
+    u128 => write_unsigned, read_unsigned
+);
+
+// usize and isize are always written as 64 bits so that files don't depend
+// on the pointer width of the machine that wrote them
+macro_rules! impl_serde_for_size {
+    ($($t:ty => $wire:ty),*) => {
+        $(
+            impl Serialize for $t {
+                fn serialize<W: Writer + ?Sized>(&self, writer: &mut W) {
+                    (*self as $wire).serialize(writer)
+                }
+            }
+
+            impl<'de> DeSerialize<'de> for $t {
+                fn deserialize<R: Reader<'de> + ?Sized>(reader: &mut R) -> Result<Self, DeserializeError> {
+                    let start = reader.position();
+                    let value = <$wire>::deserialize(reader)?;
+
+                    <$t>::try_from(value).map_err(|_| DeserializeError::OutOfRange {
+                        offset: start,
+                        ty: stringify!($t),
+                    })
+                }
+            }
+        )*
+    };
+}
+
+impl_serde_for_size!(
+    usize => u64,
+    isize => i64
+);
+
+impl Serialize for bool {
+    fn serialize<W: Writer + ?Sized>(&self, writer: &mut W) {
+        (*self as u8).serialize(writer)
+    }
+}
+
+impl<'de> DeSerialize<'de> for bool {
+    fn deserialize<R: Reader<'de> + ?Sized>(reader: &mut R) -> Result<Self, DeserializeError> {
+        let start = reader.position();
+
+        match u8::deserialize(reader)? {
+            0 => Ok(false),
+            1 => Ok(true),
+            tag => Err(DeserializeError::InvalidTag { offset: start, tag: tag as u64 }),
+        }
+    }
+}
+
+impl Serialize for char {
+    fn serialize<W: Writer + ?Sized>(&self, writer: &mut W) {
+        (*self as u32).serialize(writer)
+    }
+}
+
+impl<'de> DeSerialize<'de> for char {
+    fn deserialize<R: Reader<'de> + ?Sized>(reader: &mut R) -> Result<Self, DeserializeError> {
+        let start = reader.position();
+        let value = u32::deserialize(reader)?;
+
+        char::from_u32(value).ok_or(DeserializeError::InvalidChar { offset: start, value })
+    }
+}
+
+impl Serialize for () {
+    fn serialize<W: Writer + ?Sized>(&self, _writer: &mut W) {}
+}
+
+impl<'de> DeSerialize<'de> for () {
+    const MIN_SIZE: usize = 0;
+
+    fn deserialize<R: Reader<'de> + ?Sized>(_reader: &mut R) -> Result<Self, DeserializeError> {
+        Ok(())
+    }
+}
+
+impl<T: Serialize> Serialize for Option<T> {
+    fn serialize<W: Writer + ?Sized>(&self, writer: &mut W) {
+        match self {
+            None => 0u8.serialize(writer),
+            Some(value) => {
+                1u8.serialize(writer);
+                value.serialize(writer)
+            }
+        }
+    }
+}
+
+impl<'de, T: DeSerialize<'de>> DeSerialize<'de> for Option<T> {
+    fn deserialize<R: Reader<'de> + ?Sized>(reader: &mut R) -> Result<Self, DeserializeError> {
+        let start = reader.position();
+
+        match u8::deserialize(reader)? {
+            0 => Ok(None),
+            1 => Ok(Some(T::deserialize(reader)?)),
+            tag => Err(DeserializeError::InvalidTag { offset: start, tag: tag as u64 }),
+        }
+    }
+}
+
+macro_rules! impl_serde_for_tuple {
+    ($(($($name:ident),+)),*) => {
+        $(
+            impl<$($name: Serialize),+> Serialize for ($($name,)+) {
+                #[allow(non_snake_case)]
+                fn serialize<W: Writer + ?Sized>(&self, writer: &mut W) {
+                    let ($($name,)+) = self;
+                    $($name.serialize(writer);)+
+                }
+            }
+
+            impl<'de, $($name: DeSerialize<'de>),+> DeSerialize<'de> for ($($name,)+) {
+                const MIN_SIZE: usize = 0 $(+ $name::MIN_SIZE)+;
+
+                fn deserialize<R: Reader<'de> + ?Sized>(reader: &mut R) -> Result<Self, DeserializeError> {
+                    Ok(($($name::deserialize(reader)?,)+))
+                }
+            }
+        )*
+    };
+}
+
+impl_serde_for_tuple!(
+    (A),
+    (A, B),
+    (A, B, C),
+    (A, B, C, D),
+    (A, B, C, D, E),
+    (A, B, C, D, E, F),
+    (A, B, C, D, E, F, G),
+    (A, B, C, D, E, F, G, H),
+    (A, B, C, D, E, F, G, H, I),
+    (A, B, C, D, E, F, G, H, I, J),
+    (A, B, C, D, E, F, G, H, I, J, K),
+    (A, B, C, D, E, F, G, H, I, J, K, L)
+);
+
+// arrays have a fixed length, so unlike slices they carry no length prefix
+impl<T: Serialize, const N: usize> Serialize for [T; N] {
+    fn serialize<W: Writer + ?Sized>(&self, writer: &mut W) {
+        for item in self.iter() {
+            item.serialize(writer)
+        }
+    }
+}
+
+impl<'de, T: DeSerialize<'de>, const N: usize> DeSerialize<'de> for [T; N] {
+    const MIN_SIZE: usize = N.saturating_mul(T::MIN_SIZE);
+
+    fn deserialize<R: Reader<'de> + ?Sized>(reader: &mut R) -> Result<Self, DeserializeError> {
+        let mut items = Vec::with_capacity(N);
+        for _ in 0..N {
+            items.push(T::deserialize(reader)?);
+        }
+
+        match items.try_into() {
+            Ok(array) => Ok(array),
+            Err(_) => unreachable!("exactly N items were decoded"),
+        }
+    }
+}
+
+impl<T: Serialize + ?Sized> Serialize for &T {
+    fn serialize<W: Writer + ?Sized>(&self, writer: &mut W) {
+        (**self).serialize(writer)
+    }
+}
+
+impl<T: Serialize> Serialize for [T] {
+    fn serialize<W: Writer + ?Sized>(&self, writer: &mut W) {
+        (self.len() as u64).serialize(writer);
     
         for item in self.iter() {
-            item.serialize(sbi)
+            item.serialize(writer)
         }
     }
 }
 
-impl<T: DeSerialize> DeSerialize for Vec<T> {
-    fn deserialize(sbi: &mut SBI, offset: &mut usize) -> Result<Self, ()> where Self: Sized {
-        let len = u64::deserialize(sbi, offset)?;
+impl<T: Serialize> Serialize for Vec<T> {
+    fn serialize<W: Writer + ?Sized>(&self, writer: &mut W) {
+        self.as_slice().serialize(writer)
+    }
+}
+
+impl<'de, T: DeSerialize<'de>> DeSerialize<'de> for Vec<T> {
+    fn deserialize<R: Reader<'de> + ?Sized>(reader: &mut R) -> Result<Self, DeserializeError> {
+        let len = reader.read_len(T::MIN_SIZE)?;
 
-        let mut ret = Vec::with_capacity(len as usize);
+        let mut ret = Vec::with_capacity(capacity_hint::<T>(len));
         for _ in 0..len {
-            ret.push(T::deserialize(sbi, offset)?);
+            ret.push(T::deserialize(reader)?);
         }
 
         Ok(ret)
     }
 }
 
-impl Serialize for String {
-    fn serialize(&self, sbi: &mut SBI) {
-        (self.len() as u64).serialize(sbi);
+/// Borrows the bytes of a `Vec<u8>` encoding.
+impl<'de: 'a, 'a> DeSerialize<'de> for &'a [u8] {
+    fn deserialize<R: Reader<'de> + ?Sized>(reader: &mut R) -> Result<Self, DeserializeError> {
+        let len = reader.read_len(1)?;
+        reader.read_bytes(len)
+    }
+}
+
+impl Serialize for str {
+    fn serialize<W: Writer + ?Sized>(&self, writer: &mut W) {
+        (self.len() as u64).serialize(writer);
         
-        sbi.data.extend_from_slice(self.as_bytes())
+        writer.write_bytes(self.as_bytes())
+    }
+}
+
+impl Serialize for String {
+    fn serialize<W: Writer + ?Sized>(&self, writer: &mut W) {
+        self.as_str().serialize(writer)
     }
 }
 
-impl DeSerialize for String {
-    fn deserialize(sbi: &mut SBI, offset: &mut usize) -> Result<Self, ()> where Self: Sized {
-        let len = u64::deserialize(sbi, offset)?;
+impl<'de> DeSerialize<'de> for String {
+    fn deserialize<R: Reader<'de> + ?Sized>(reader: &mut R) -> Result<Self, DeserializeError> {
+        Cow::<str>::deserialize(reader).map(Cow::into_owned)
+    }
+}
+
+/// Always borrows, so invalid UTF-8 is an error even when `Config::lossy_utf8`
+/// is set; decode into `Cow<str>` to get lossy replacement.
+impl<'de: 'a, 'a> DeSerialize<'de> for &'a str {
+    fn deserialize<R: Reader<'de> + ?Sized>(reader: &mut R) -> Result<Self, DeserializeError> {
+        let len = reader.read_len(1)?;
+        let start = reader.position();
+        let bytes = reader.read_bytes(len)?;
 
-        let string = String::from_utf8_lossy(&sbi.data[*offset..*offset + len as usize]).to_string();
-        *offset += len as usize;
+        str::from_utf8(bytes).map_err(|err| DeserializeError::InvalidUtf8 { offset: start + err.valid_up_to() })
+    }
+}
 
-        Ok(string)
+impl<B: Serialize + ToOwned + ?Sized> Serialize for Cow<'_, B> {
+    fn serialize<W: Writer + ?Sized>(&self, writer: &mut W) {
+        (**self).serialize(writer)
     }
-}
\ No newline at end of file
+}
+
+impl<'de: 'a, 'a> DeSerialize<'de> for Cow<'a, str> {
+    fn deserialize<R: Reader<'de> + ?Sized>(reader: &mut R) -> Result<Self, DeserializeError> {
+        let len = reader.read_len(1)?;
+        let start = reader.position();
+        let bytes = reader.read_bytes(len)?;
+
+        match str::from_utf8(bytes) {
+            Ok(string) => Ok(Cow::Borrowed(string)),
+            Err(_) if reader.config().lossy_utf8 => Ok(String::from_utf8_lossy(bytes)),
+            Err(err) => Err(DeserializeError::InvalidUtf8 { offset: start + err.valid_up_to() }),
+        }
+    }
+}
@@ -0,0 +1,207 @@
+use std::{marker::PhantomData, ops::Range};
+
+use crate::{Config, DeSerialize, DeserializeError, ReadRefTable, Reader};
+
+/// Reader over an in-memory buffer that keeps track of how far it has read.
+///
+/// Each call to `deserialize` decodes one root value starting where the
+/// previous one ended, so a buffer holding several consecutive values can be
+/// decoded one after the other, and `remaining` tells how many bytes are left
+/// over after the last.
+#[derive(Debug)]
+pub struct Cursor<'de> {
+    data: &'de [u8],
+    position: usize,
+    config: Config,
+    refs: ReadRefTable,
+}
+
+impl<'de> Cursor<'de> {
+    pub fn new(data: &'de [u8]) -> Self {
+        Self::with_config(data, Config::default())
+    }
+
+    pub fn with_config(data: &'de [u8], config: Config) -> Self {
+        Self {
+            data,
+            position: 0,
+            config,
+            refs: ReadRefTable::default(),
+        }
+    }
+
+    /// Cursor limited to `range` of `data`. Positions, and with them error
+    /// offsets, still count from the start of `data`.
+    ///
+    /// Panics if `range` is out of bounds.
+    pub fn region(data: &'de [u8], range: Range<usize>, config: Config) -> Self {
+        assert!(
+            range.start <= range.end && range.end <= data.len(),
+            "region {range:?} is out of bounds for {} bytes",
+            data.len()
+        );
+
+        Self {
+            position: range.start,
+            ..Self::with_config(&data[..range.end], config)
+        }
+    }
+
+    /// Decodes the next root value.
+    pub fn deserialize<T: DeSerialize<'de>>(&mut self) -> Result<T, DeserializeError> {
+        self.refs.clear();
+        T::deserialize(self)
+    }
+
+    /// The bytes that haven't been read yet.
+    pub fn rest(&self) -> &'de [u8] {
+        &self.data[self.position..]
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.position == self.data.len()
+    }
+
+    /// Fails with `DeserializeError::TrailingBytes` unless every byte has
+    /// been read.
+    pub fn end(&self) -> Result<(), DeserializeError> {
+        match self.remaining() {
+            0 => Ok(()),
+            len => Err(DeserializeError::TrailingBytes {
+                offset: self.position,
+                len,
+            }),
+        }
+    }
+}
+
+impl<'de> Reader<'de> for Cursor<'de> {
+    fn config(&self) -> &Config {
+        &self.config
+    }
+
+    fn position(&self) -> usize {
+        self.position
+    }
+
+    fn remaining(&self) -> usize {
+        self.data.len() - self.position
+    }
+
+    fn peek(&self, len: usize) -> Result<&'de [u8], DeserializeError> {
+        if len > self.remaining() {
+            return Err(DeserializeError::UnexpectedEof {
+                offset: self.position,
+                needed: len,
+            });
+        }
+
+        Ok(&self.data[self.position..self.position + len])
+    }
+
+    fn skip(&mut self, len: usize) -> Result<(), DeserializeError> {
+        self.peek(len)?;
+        self.position += len;
+        Ok(())
+    }
+
+    fn refs(&mut self) -> &mut ReadRefTable {
+        &mut self.refs
+    }
+}
+
+/// Read-only bytes paired with a `Config`, so that anything exposing its
+/// contents as `&[u8]`, like a memory-mapped file, can be decoded without
+/// first being copied into an `SBI`.
+pub struct ByteSource<B> {
+    bytes: B,
+    config: Config,
+}
+
+impl<B: AsRef<[u8]>> ByteSource<B> {
+    pub fn new(bytes: B) -> Self {
+        Self::with_config(bytes, Config::default())
+    }
+
+    pub fn with_config(bytes: B, config: Config) -> Self {
+        Self { bytes, config }
+    }
+
+    pub fn deserialize<'de, T: DeSerialize<'de>>(&'de self) -> Result<T, DeserializeError> {
+        let mut cursor = self.cursor();
+        let value = cursor.deserialize()?;
+
+        if self.config.reject_trailing_bytes {
+            cursor.end()?;
+        }
+
+        Ok(value)
+    }
+
+    pub fn iter<'de, T: DeSerialize<'de>>(&'de self) -> Values<'de, T> {
+        Values::new(self.cursor())
+    }
+
+    pub fn cursor(&self) -> Cursor<'_> {
+        Cursor::with_config(self.bytes.as_ref(), self.config)
+    }
+
+    /// Cursor over just `range` of the bytes, see `SBI::region`.
+    pub fn region(&self, range: Range<usize>) -> Cursor<'_> {
+        Cursor::region(self.bytes.as_ref(), range, self.config)
+    }
+
+    pub fn into_inner(self) -> B {
+        self.bytes
+    }
+}
+
+/// Iterator over root values stored back to back, see `SBI::iter`.
+///
+/// Stops once the buffer is exhausted. A value that fails to decode is
+/// yielded as an error and ends the iteration, since there is no telling
+/// where the next value would start. So does a value that takes no bytes
+/// while data is left, such as `()`, since it would never get past it.
+pub struct Values<'de, T> {
+    cursor: Cursor<'de>,
+    failed: bool,
+    _marker: PhantomData<fn() -> T>,
+}
+
+impl<'de, T> Values<'de, T> {
+    pub fn new(cursor: Cursor<'de>) -> Self {
+        Self {
+            cursor,
+            failed: false,
+            _marker: PhantomData,
+        }
+    }
+
+    /// Offset of the next value in the buffer.
+    pub fn position(&self) -> usize {
+        self.cursor.position
+    }
+}
+
+impl<'de, T: DeSerialize<'de>> Iterator for Values<'de, T> {
+    type Item = Result<T, DeserializeError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.failed || self.cursor.is_empty() {
+            return None;
+        }
+
+        let start = self.cursor.position;
+        let result = self.cursor.deserialize().and_then(|value| {
+            if self.cursor.position == start {
+                return Err(DeserializeError::TrailingBytes {
+                    offset: start,
+                    len: self.cursor.remaining(),
+                });
+            }
+            Ok(value)
+        });
+        self.failed = result.is_err();
+        Some(result)
+    }
+}
@@ -0,0 +1,666 @@
+use std::{
+    borrow::Cow,
+    collections::{BTreeMap, BTreeSet, HashMap, HashSet, VecDeque},
+    fmt,
+    fmt::Write as _,
+    num::{
+        NonZeroI128, NonZeroI16, NonZeroI32, NonZeroI64, NonZeroI8, NonZeroIsize, NonZeroU128,
+        NonZeroU16, NonZeroU32, NonZeroU64, NonZeroU8, NonZeroUsize, Wrapping,
+    },
+    rc::Rc,
+    sync::Arc,
+    time::Duration,
+};
+
+use crate::{
+    capacity_hint, ByteOrder, Config, DeSerialize, DeserializeError, Discriminant, IntEncoding,
+    Migrate, Reader, Serialize, Versioned, Writer,
+};
+
+/// Description of the layout a type serializes to.
+///
+/// Schemas serialize like any other value, so one can be stored in front of
+/// the data it describes, and `to_json` exports them for other tools.
+/// `validate` checks a buffer against a schema without needing the Rust type,
+/// and `dump` lists its contents.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Schema {
+    Unit,
+    Bool,
+    U8,
+    U16,
+    U32,
+    U64,
+    U128,
+    I8,
+    I16,
+    I32,
+    I64,
+    I128,
+    F32,
+    F64,
+    Char,
+    String,
+    Option(Box<Schema>),
+    /// Length-prefixed sequence, like `Vec` or `HashSet`.
+    Seq(Box<Schema>),
+    /// Length-prefixed `(key, value)` entries.
+    Map(Box<Schema>, Box<Schema>),
+    /// Fixed number of items without a length prefix.
+    Array(Box<Schema>, u64),
+    Tuple(Vec<Schema>),
+    Struct {
+        name: String,
+        fields: Vec<Field>,
+    },
+    /// Struct using the `#[sbs(tagged)]` encoding; each field has an id.
+    Tagged {
+        name: String,
+        fields: Vec<(u32, Field)>,
+    },
+    Enum {
+        name: String,
+        variants: Vec<Variant>,
+    },
+    /// `Rc` or `Arc`, which carry a reference tag when `Config::track_refs`
+    /// is set.
+    Shared(Box<Schema>),
+    /// `Versioned` value; only the given version is described.
+    Versioned(u32, Box<Schema>),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Field {
+    pub name: String,
+    pub schema: Schema,
+}
+
+impl Field {
+    pub fn new(name: impl Into<String>, schema: Schema) -> Self {
+        Self {
+            name: name.into(),
+            schema,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Variant {
+    pub name: String,
+    pub discriminant: u64,
+    pub fields: Vec<Field>,
+}
+
+impl Variant {
+    pub fn new(name: impl Into<String>, discriminant: u64, fields: Vec<Field>) -> Self {
+        Self {
+            name: name.into(),
+            discriminant,
+            fields,
+        }
+    }
+}
+
+/// Types that can describe their own layout. Derivable alongside
+/// `Serialize` and `DeSerialize`.
+///
+/// Recursive types would describe themselves endlessly and can't implement
+/// this.
+pub trait Describe {
+    fn schema() -> Schema;
+}
+
+macro_rules! impl_describe {
+    ($($t:ty => $schema:expr),* $(,)?) => {
+        $(
+            impl Describe for $t {
+                fn schema() -> Schema {
+                    $schema
+                }
+            }
+        )*
+    };
+}
+
+impl_describe!(
+    () => Schema::Unit,
+    bool => Schema::Bool,
+    u8 => Schema::U8,
+    u16 => Schema::U16,
+    u32 => Schema::U32,
+    u64 => Schema::U64,
+    u128 => Schema::U128,
+    usize => Schema::U64,
+    i8 => Schema::I8,
+    i16 => Schema::I16,
+    i32 => Schema::I32,
+    i64 => Schema::I64,
+    i128 => Schema::I128,
+    isize => Schema::I64,
+    f32 => Schema::F32,
+    f64 => Schema::F64,
+    char => Schema::Char,
+    str => Schema::String,
+    String => Schema::String,
+    NonZeroU8 => Schema::U8,
+    NonZeroU16 => Schema::U16,
+    NonZeroU32 => Schema::U32,
+    NonZeroU64 => Schema::U64,
+    NonZeroU128 => Schema::U128,
+    NonZeroUsize => Schema::U64,
+    NonZeroI8 => Schema::I8,
+    NonZeroI16 => Schema::I16,
+    NonZeroI32 => Schema::I32,
+    NonZeroI64 => Schema::I64,
+    NonZeroI128 => Schema::I128,
+    NonZeroIsize => Schema::I64,
+    Duration => Schema::Struct {
+        name: "Duration".into(),
+        fields: vec![Field::new("secs", Schema::U64), Field::new("nanos", Schema::U32)],
+    },
+);
+
+macro_rules! impl_describe_generic {
+    ($($t:ty => |$inner:ident| $schema:expr),* $(,)?) => {
+        $(
+            impl<T: Describe + ?Sized> Describe for $t {
+                fn schema() -> Schema {
+                    let $inner = T::schema();
+                    $schema
+                }
+            }
+        )*
+    };
+}
+
+impl_describe_generic!(
+    &T => |schema| schema,
+    Box<T> => |schema| schema,
+    Rc<T> => |schema| Schema::Shared(Box::new(schema)),
+    Arc<T> => |schema| Schema::Shared(Box::new(schema)),
+);
+
+macro_rules! impl_describe_seq {
+    ($($t:ty),*) => {
+        $(
+            impl<T: Describe> Describe for $t {
+                fn schema() -> Schema {
+                    Schema::Seq(Box::new(T::schema()))
+                }
+            }
+        )*
+    };
+}
+
+impl_describe_seq!([T], Vec<T>, VecDeque<T>, BTreeSet<T>);
+
+impl<B: Describe + ToOwned + ?Sized> Describe for Cow<'_, B> {
+    fn schema() -> Schema {
+        B::schema()
+    }
+}
+
+impl<T: Describe, S> Describe for HashSet<T, S> {
+    fn schema() -> Schema {
+        Schema::Seq(Box::new(T::schema()))
+    }
+}
+
+impl<K: Describe, V: Describe> Describe for BTreeMap<K, V> {
+    fn schema() -> Schema {
+        Schema::Map(Box::new(K::schema()), Box::new(V::schema()))
+    }
+}
+
+impl<K: Describe, V: Describe, S> Describe for HashMap<K, V, S> {
+    fn schema() -> Schema {
+        Schema::Map(Box::new(K::schema()), Box::new(V::schema()))
+    }
+}
+
+impl<T: Describe> Describe for Option<T> {
+    fn schema() -> Schema {
+        Schema::Option(Box::new(T::schema()))
+    }
+}
+
+impl<T: Describe> Describe for Wrapping<T> {
+    fn schema() -> Schema {
+        T::schema()
+    }
+}
+
+impl<T: Describe, const N: usize> Describe for [T; N] {
+    fn schema() -> Schema {
+        Schema::Array(Box::new(T::schema()), N as u64)
+    }
+}
+
+impl<T: Describe + Migrate> Describe for Versioned<T> {
+    fn schema() -> Schema {
+        Schema::Versioned(T::VERSION, Box::new(T::schema()))
+    }
+}
+
+macro_rules! impl_describe_tuple {
+    ($(($($name:ident),+)),*) => {
+        $(
+            impl<$($name: Describe),+> Describe for ($($name,)+) {
+                fn schema() -> Schema {
+                    Schema::Tuple(vec![$($name::schema()),+])
+                }
+            }
+        )*
+    };
+}
+
+impl_describe_tuple!(
+    (A),
+    (A, B),
+    (A, B, C),
+    (A, B, C, D),
+    (A, B, C, D, E),
+    (A, B, C, D, E, F),
+    (A, B, C, D, E, F, G),
+    (A, B, C, D, E, F, G, H),
+    (A, B, C, D, E, F, G, H, I),
+    (A, B, C, D, E, F, G, H, I, J),
+    (A, B, C, D, E, F, G, H, I, J, K),
+    (A, B, C, D, E, F, G, H, I, J, K, L)
+);
+
+// Schemas are encoded as a discriminant per node, in the order of the
+// `Schema` variants, followed by the node's contents.
+
+impl Serialize for Schema {
+    fn serialize<W: Writer + ?Sized>(&self, writer: &mut W) {
+        let tag = |n: u64, writer: &mut W| Discriminant(n).serialize(writer);
+
+        match self {
+            Self::Unit => tag(0, writer),
+            Self::Bool => tag(1, writer),
+            Self::U8 => tag(2, writer),
+            Self::U16 => tag(3, writer),
+            Self::U32 => tag(4, writer),
+            Self::U64 => tag(5, writer),
+            Self::U128 => tag(6, writer),
+            Self::I8 => tag(7, writer),
+            Self::I16 => tag(8, writer),
+            Self::I32 => tag(9, writer),
+            Self::I64 => tag(10, writer),
+            Self::I128 => tag(11, writer),
+            Self::F32 => tag(12, writer),
+            Self::F64 => tag(13, writer),
+            Self::Char => tag(14, writer),
+            Self::String => tag(15, writer),
+            Self::Option(item) => {
+                tag(16, writer);
+                item.serialize(writer)
+            }
+            Self::Seq(item) => {
+                tag(17, writer);
+                item.serialize(writer)
+            }
+            Self::Map(key, value) => {
+                tag(18, writer);
+                key.serialize(writer);
+                value.serialize(writer)
+            }
+            Self::Array(item, len) => {
+                tag(19, writer);
+                item.serialize(writer);
+                len.serialize(writer)
+            }
+            Self::Tuple(items) => {
+                tag(20, writer);
+                items.serialize(writer)
+            }
+            Self::Struct { name, fields } => {
+                tag(21, writer);
+                name.serialize(writer);
+                fields.serialize(writer)
+            }
+            Self::Tagged { name, fields } => {
+                tag(22, writer);
+                name.serialize(writer);
+                fields.serialize(writer)
+            }
+            Self::Enum { name, variants } => {
+                tag(23, writer);
+                name.serialize(writer);
+                variants.serialize(writer)
+            }
+            Self::Shared(item) => {
+                tag(24, writer);
+                item.serialize(writer)
+            }
+            Self::Versioned(version, item) => {
+                tag(25, writer);
+                version.serialize(writer);
+                item.serialize(writer)
+            }
+        }
+    }
+}
+
+/// Deepest nesting of schema nodes accepted when decoding a schema, so a
+/// corrupt one can't overflow the stack.
+const MAX_DEPTH: usize = 128;
+
+impl<'de> DeSerialize<'de> for Schema {
+    fn deserialize<R: Reader<'de> + ?Sized>(reader: &mut R) -> Result<Self, DeserializeError> {
+        Self::read(reader, 0)
+    }
+}
+
+impl Schema {
+    /// Decodes a node `depth` levels below the root.
+    fn read<'de, R: Reader<'de> + ?Sized>(
+        reader: &mut R,
+        depth: usize,
+    ) -> Result<Self, DeserializeError> {
+        let start = reader.position();
+        if depth >= MAX_DEPTH {
+            return Err(DeserializeError::TooDeep {
+                offset: start,
+                limit: MAX_DEPTH,
+            });
+        }
+
+        let discriminant = Discriminant::deserialize(reader)?;
+        let item = |reader: &mut R| Self::read(reader, depth + 1).map(Box::new);
+
+        Ok(match discriminant.0 {
+            0 => Self::Unit,
+            1 => Self::Bool,
+            2 => Self::U8,
+            3 => Self::U16,
+            4 => Self::U32,
+            5 => Self::U64,
+            6 => Self::U128,
+            7 => Self::I8,
+            8 => Self::I16,
+            9 => Self::I32,
+            10 => Self::I64,
+            11 => Self::I128,
+            12 => Self::F32,
+            13 => Self::F64,
+            14 => Self::Char,
+            15 => Self::String,
+            16 => Self::Option(item(reader)?),
+            17 => Self::Seq(item(reader)?),
+            18 => Self::Map(item(reader)?, item(reader)?),
+            19 => Self::Array(item(reader)?, DeSerialize::deserialize(reader)?),
+            20 => Self::Tuple(read_seq(reader, |reader| Self::read(reader, depth + 1))?),
+            21 => Self::Struct {
+                name: DeSerialize::deserialize(reader)?,
+                fields: read_seq(reader, |reader| Field::read(reader, depth + 1))?,
+            },
+            22 => Self::Tagged {
+                name: DeSerialize::deserialize(reader)?,
+                fields: read_seq(reader, |reader| {
+                    Ok((u32::deserialize(reader)?, Field::read(reader, depth + 1)?))
+                })?,
+            },
+            23 => Self::Enum {
+                name: DeSerialize::deserialize(reader)?,
+                variants: read_seq(reader, |reader| Variant::read(reader, depth + 1))?,
+            },
+            24 => Self::Shared(item(reader)?),
+            25 => Self::Versioned(DeSerialize::deserialize(reader)?, item(reader)?),
+            _ => return Err(discriminant.invalid(start)),
+        })
+    }
+}
+
+/// Decodes a `Vec` of schema parts, passing the nesting depth down.
+fn read_seq<'de, R, T, F>(reader: &mut R, mut read: F) -> Result<Vec<T>, DeserializeError>
+where
+    R: Reader<'de> + ?Sized,
+    F: FnMut(&mut R) -> Result<T, DeserializeError>,
+{
+    let len = reader.read_len(1)?;
+    let mut items = Vec::with_capacity(capacity_hint::<T>(len));
+    for _ in 0..len {
+        items.push(read(reader)?);
+    }
+    Ok(items)
+}
+
+impl Serialize for Field {
+    fn serialize<W: Writer + ?Sized>(&self, writer: &mut W) {
+        self.name.serialize(writer);
+        self.schema.serialize(writer)
+    }
+}
+
+impl<'de> DeSerialize<'de> for Field {
+    fn deserialize<R: Reader<'de> + ?Sized>(reader: &mut R) -> Result<Self, DeserializeError> {
+        Self::read(reader, 0)
+    }
+}
+
+impl Field {
+    fn read<'de, R: Reader<'de> + ?Sized>(
+        reader: &mut R,
+        depth: usize,
+    ) -> Result<Self, DeserializeError> {
+        Ok(Self {
+            name: String::deserialize(reader)?,
+            schema: Schema::read(reader, depth)?,
+        })
+    }
+}
+
+impl Serialize for Variant {
+    fn serialize<W: Writer + ?Sized>(&self, writer: &mut W) {
+        self.name.serialize(writer);
+        Discriminant(self.discriminant).serialize(writer);
+        self.fields.serialize(writer)
+    }
+}
+
+impl<'de> DeSerialize<'de> for Variant {
+    fn deserialize<R: Reader<'de> + ?Sized>(reader: &mut R) -> Result<Self, DeserializeError> {
+        Self::read(reader, 0)
+    }
+}
+
+impl Variant {
+    fn read<'de, R: Reader<'de> + ?Sized>(
+        reader: &mut R,
+        depth: usize,
+    ) -> Result<Self, DeserializeError> {
+        Ok(Self {
+            name: String::deserialize(reader)?,
+            discriminant: Discriminant::deserialize(reader)?.0,
+            fields: read_seq(reader, |reader| Field::read(reader, depth))?,
+        })
+    }
+}
+
+impl Schema {
+    /// Exports the schema as JSON, together with the parts of `config` that
+    /// decide how it is encoded.
+    pub fn to_json(&self, config: &Config) -> String {
+        let byte_order = match config.byte_order {
+            ByteOrder::Big => "big",
+            ByteOrder::Little => "little",
+            ByteOrder::Native => "native",
+        };
+        let int_encoding = match config.int_encoding {
+            IntEncoding::Fixed => "fixed",
+            IntEncoding::Varint => "varint",
+        };
+
+        let mut out = String::new();
+        write!(
+            out,
+            r#"{{"byte_order":"{byte_order}","int_encoding":"{int_encoding}","track_refs":{},"schema":"#,
+            config.track_refs
+        )
+        .unwrap();
+        self.write_json(&mut out);
+        out.push('}');
+        out
+    }
+
+    fn write_json(&self, out: &mut String) {
+        let kind = |out: &mut String, kind: &str| write!(out, r#"{{"type":"{kind}""#).unwrap();
+
+        match self {
+            Self::Option(item) | Self::Seq(item) | Self::Shared(item) => {
+                kind(out, self.kind());
+                out.push_str(r#","item":"#);
+                item.write_json(out);
+            }
+            Self::Map(key, value) => {
+                kind(out, "map");
+                out.push_str(r#","key":"#);
+                key.write_json(out);
+                out.push_str(r#","value":"#);
+                value.write_json(out);
+            }
+            Self::Array(item, len) => {
+                kind(out, "array");
+                write!(out, r#","len":{len},"item":"#).unwrap();
+                item.write_json(out);
+            }
+            Self::Tuple(items) => {
+                kind(out, "tuple");
+                out.push_str(r#","items":["#);
+                for (i, item) in items.iter().enumerate() {
+                    if i > 0 {
+                        out.push(',');
+                    }
+                    item.write_json(out);
+                }
+                out.push(']');
+            }
+            Self::Struct { name, fields } => {
+                kind(out, "struct");
+                write!(out, r#","name":{},"fields":"#, JsonStr(name)).unwrap();
+                write_fields_json(fields.iter().map(|field| (None, field)), out);
+            }
+            Self::Tagged { name, fields } => {
+                kind(out, "tagged");
+                write!(out, r#","name":{},"fields":"#, JsonStr(name)).unwrap();
+                write_fields_json(fields.iter().map(|(id, field)| (Some(*id), field)), out);
+            }
+            Self::Enum { name, variants } => {
+                kind(out, "enum");
+                write!(out, r#","name":{},"variants":["#, JsonStr(name)).unwrap();
+                for (i, variant) in variants.iter().enumerate() {
+                    if i > 0 {
+                        out.push(',');
+                    }
+                    write!(
+                        out,
+                        r#"{{"name":{},"discriminant":{},"fields":"#,
+                        JsonStr(&variant.name),
+                        variant.discriminant
+                    )
+                    .unwrap();
+                    write_fields_json(variant.fields.iter().map(|field| (None, field)), out);
+                    out.push('}');
+                }
+                out.push(']');
+            }
+            Self::Versioned(version, item) => {
+                kind(out, "versioned");
+                write!(out, r#","version":{version},"item":"#).unwrap();
+                item.write_json(out);
+            }
+            _ => kind(out, self.kind()),
+        }
+        out.push('}');
+    }
+
+    /// Smallest number of bytes a value can encode to, capped at 1, for
+    /// checking length prefixes.
+    pub(crate) fn min_size(&self) -> usize {
+        match self {
+            Self::Unit => 0,
+            Self::Array(item, len) => match len {
+                0 => 0,
+                _ => item.min_size(),
+            },
+            Self::Tuple(items) => items.iter().map(Self::min_size).max().unwrap_or(0),
+            Self::Struct { fields, .. } => fields
+                .iter()
+                .map(|field| field.schema.min_size())
+                .max()
+                .unwrap_or(0),
+            Self::Shared(item) => item.min_size(),
+            _ => 1,
+        }
+    }
+
+    /// Short name of the kind of value this describes, like `"u32"` or
+    /// `"struct"`.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Self::Unit => "unit",
+            Self::Bool => "bool",
+            Self::U8 => "u8",
+            Self::U16 => "u16",
+            Self::U32 => "u32",
+            Self::U64 => "u64",
+            Self::U128 => "u128",
+            Self::I8 => "i8",
+            Self::I16 => "i16",
+            Self::I32 => "i32",
+            Self::I64 => "i64",
+            Self::I128 => "i128",
+            Self::F32 => "f32",
+            Self::F64 => "f64",
+            Self::Char => "char",
+            Self::String => "string",
+            Self::Option(_) => "option",
+            Self::Seq(_) => "seq",
+            Self::Map(..) => "map",
+            Self::Array(..) => "array",
+            Self::Tuple(_) => "tuple",
+            Self::Struct { .. } => "struct",
+            Self::Tagged { .. } => "tagged",
+            Self::Enum { .. } => "enum",
+            Self::Shared(_) => "shared",
+            Self::Versioned(..) => "versioned",
+        }
+    }
+}
+
+fn write_fields_json<'a>(fields: impl Iterator<Item = (Option<u32>, &'a Field)>, out: &mut String) {
+    out.push('[');
+    for (i, (id, field)) in fields.enumerate() {
+        if i > 0 {
+            out.push(',');
+        }
+        write!(out, r#"{{"name":{}"#, JsonStr(&field.name)).unwrap();
+        if let Some(id) = id {
+            write!(out, r#","id":{id}"#).unwrap();
+        }
+        out.push_str(r#","schema":"#);
+        field.schema.write_json(out);
+        out.push('}');
+    }
+    out.push(']');
+}
+
+/// Writes a string as a quoted JSON string.
+struct JsonStr<'a>(&'a str);
+
+impl fmt::Display for JsonStr<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_char('"')?;
+        for c in self.0.chars() {
+            match c {
+                '"' => f.write_str("\\\"")?,
+                '\\' => f.write_str("\\\\")?,
+                c if (c as u32) < 0x20 => write!(f, "\\u{:04x}", c as u32)?,
+                c => f.write_char(c)?,
+            }
+        }
+        f.write_char('"')
+    }
+}
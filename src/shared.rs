@@ -0,0 +1,99 @@
+use std::{any::Any, collections::HashMap, fmt};
+
+use crate::{varint, DeserializeError, Reader, Writer};
+
+// With `Config::track_refs` set, every `Rc` and `Arc` is preceded by a varint
+// tag. A tag of 0 means the pointee follows inline and is assigned the next
+// id; any other tag `n` refers back to the pointee that was given id `n - 1`.
+// Ids are handed out after the pointee has been written, in the order the
+// pointers are encountered, which is the same order they are read back in.
+//
+// Ids only cover a single root passed to `SBI::serialize`,
+// `IoWriter::serialize` or `Cursor::deserialize`.
+
+/// Ids handed out to the shared allocations a `Writer` has written so far,
+/// used when `Config::track_refs` is set.
+#[derive(Debug, Default)]
+pub struct RefTable {
+    ids: HashMap<usize, u64>,
+}
+
+impl RefTable {
+    /// Forgets every allocation seen so far; called before each new root.
+    pub fn clear(&mut self) {
+        self.ids.clear()
+    }
+}
+
+/// Shared values a `Reader` has decoded so far, indexed by id, used when
+/// `Config::track_refs` is set.
+#[derive(Default)]
+pub struct ReadRefTable {
+    values: Vec<Box<dyn Any>>,
+}
+
+impl ReadRefTable {
+    /// Forgets every value decoded so far; called before each new root.
+    pub fn clear(&mut self) {
+        self.values.clear()
+    }
+}
+
+impl fmt::Debug for ReadRefTable {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ReadRefTable")
+            .field("len", &self.values.len())
+            .finish()
+    }
+}
+
+pub(crate) fn serialize_shared<W: Writer + ?Sized>(
+    address: usize,
+    writer: &mut W,
+    write: impl FnOnce(&mut W),
+) {
+    if !writer.config().track_refs {
+        return write(writer);
+    }
+
+    if let Some(id) = writer.refs().ids.get(&address) {
+        let tag = *id + 1;
+        return varint::write_unsigned(tag as u128, writer);
+    }
+
+    varint::write_unsigned(0, writer);
+    write(writer);
+
+    let refs = writer.refs();
+    let id = refs.ids.len() as u64;
+    refs.ids.insert(address, id);
+}
+
+pub(crate) fn deserialize_shared<'de, P: Clone + 'static, R: Reader<'de> + ?Sized>(
+    reader: &mut R,
+    read: impl FnOnce(&mut R) -> Result<P, DeserializeError>,
+) -> Result<P, DeserializeError> {
+    if !reader.config().track_refs {
+        return read(reader);
+    }
+
+    let start = reader.position();
+    let tag = match u64::try_from(varint::read_unsigned(reader)?) {
+        Ok(tag) => tag,
+        Err(_) => return Err(DeserializeError::InvalidVarint { offset: start }),
+    };
+
+    if tag == 0 {
+        let value = read(reader)?;
+        reader.refs().values.push(Box::new(value.clone()));
+        return Ok(value);
+    }
+
+    let id = tag - 1;
+    usize::try_from(id)
+        .ok()
+        .and_then(|id| reader.refs().values.get(id))
+        .and_then(|entry| entry.downcast_ref::<P>())
+        .cloned()
+        .ok_or(DeserializeError::InvalidReference { offset: start, id })
+}
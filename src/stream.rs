@@ -0,0 +1,217 @@
+use std::{error, fmt, io, marker::PhantomData};
+
+use crate::{Config, DeSerializeOwned, DeserializeError, Reader, SBI};
+
+/// Smallest amount requested from the underlying reader at a time.
+const MIN_READ: usize = 8 * 1024;
+
+#[derive(Debug)]
+pub enum StreamError {
+    Io(io::Error),
+    Deserialize(DeserializeError),
+}
+
+impl fmt::Display for StreamError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(err) => write!(f, "failed to read from stream: {err}"),
+            Self::Deserialize(err) => err.fmt(f),
+        }
+    }
+}
+
+impl error::Error for StreamError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            Self::Io(err) => Some(err),
+            Self::Deserialize(err) => Some(err),
+        }
+    }
+}
+
+impl From<io::Error> for StreamError {
+    fn from(err: io::Error) -> Self {
+        Self::Io(err)
+    }
+}
+
+impl From<DeserializeError> for StreamError {
+    fn from(err: DeserializeError) -> Self {
+        Self::Deserialize(err)
+    }
+}
+
+/// Decodes a sequence of root values from any `io::Read`, pulling bytes in
+/// only as they are needed.
+///
+/// Only the value currently being decoded is held in memory, along with
+/// whatever has been read ahead of it, so a log of many records can be
+/// replayed without loading the whole file. A value that turns out to be
+/// incomplete is decoded again from its start once the buffer has at least
+/// doubled, so large values are only decoded a logarithmic number of times.
+/// Values are decoded into owned types, since the buffer is reused between
+/// them. Error offsets are relative to the start of the stream.
+pub struct StreamReader<R> {
+    reader: R,
+    buffer: SBI,
+    /// Where the next value starts in `buffer`. Decoded values are only
+    /// dropped from the front of the buffer once they take up more of it
+    /// than what is left, so each byte is moved a bounded number of times.
+    start: usize,
+    consumed: usize,
+    eof: bool,
+}
+
+impl<R: io::Read> StreamReader<R> {
+    pub fn new(reader: R) -> Self {
+        Self::with_config(reader, Config::default())
+    }
+
+    pub fn with_config(reader: R, config: Config) -> Self {
+        Self {
+            reader,
+            buffer: SBI::with_config(config),
+            start: 0,
+            consumed: 0,
+            eof: false,
+        }
+    }
+
+    /// Decodes the next value, or returns `None` if the stream ended cleanly
+    /// between two values. A value that takes no bytes, such as `()`, is an
+    /// error while there is data left, since reading on would never get past
+    /// it; the error counts only the bytes buffered so far.
+    pub fn read<T: DeSerializeOwned>(&mut self) -> Result<Option<T>, StreamError> {
+        loop {
+            if self.buffered() == 0 {
+                if self.eof {
+                    return Ok(None);
+                }
+
+                self.fill(1)?;
+                continue;
+            }
+
+            // positions count from the start of the buffer, not the value
+            let mut cursor = self.buffer.region(self.start..self.buffer.data.len());
+            let result = cursor.deserialize::<T>();
+            let len = cursor.position() - self.start;
+
+            match result {
+                // the next read would decode the same bytes again
+                Ok(_) if len == 0 => {
+                    let buffered = self.buffered();
+                    return Err(DeserializeError::TrailingBytes {
+                        offset: self.consumed,
+                        len: buffered,
+                    }
+                    .into());
+                }
+                Ok(value) => {
+                    self.start += len;
+                    self.consumed += len;
+                    return Ok(Some(value));
+                }
+                Err(DeserializeError::UnexpectedEof { offset, needed }) if !self.eof => {
+                    self.refill(offset + needed - self.start)?
+                }
+                // a length prefix can't be checked against bytes that
+                // haven't been read yet, so treat it like running out;
+                // `LengthTooLarge` can't be helped by reading more, so it is
+                // reported straight away
+                Err(DeserializeError::LengthOverflow { .. }) if !self.eof => {
+                    self.refill(self.buffered() + 1)?
+                }
+                Err(err) => return Err(err.shifted(self.consumed - self.start).into()),
+            }
+        }
+    }
+
+    /// Iterates over the remaining values in the stream, all of type `T`.
+    ///
+    /// Like `SBI::iter`, the iteration ends after the first error.
+    pub fn iter<T: DeSerializeOwned>(&mut self) -> StreamIter<'_, R, T> {
+        StreamIter {
+            reader: self,
+            failed: false,
+            _marker: PhantomData,
+        }
+    }
+
+    /// Number of bytes taken up by the values decoded so far.
+    pub fn position(&self) -> usize {
+        self.consumed
+    }
+
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+
+    /// Number of bytes read from the stream but not decoded yet.
+    fn buffered(&self) -> usize {
+        self.buffer.data.len() - self.start
+    }
+
+    /// Buffers more bytes for a value that turned out to be incomplete:
+    /// at least `required`, and at least twice as many as are buffered now,
+    /// so a value spanning many reads is only decoded again O(log n) times.
+    fn refill(&mut self, required: usize) -> io::Result<()> {
+        let doubled = 2 * self.buffered();
+        self.fill(required.max(doubled))
+    }
+
+    /// Reads until at least `required` bytes past the decoded ones are
+    /// buffered or the stream ends. Each read asks for as many bytes as are
+    /// already buffered, so sources that have data ready fill the buffer in
+    /// doubling steps.
+    fn fill(&mut self, required: usize) -> io::Result<()> {
+        if self.start > self.buffered() {
+            self.buffer.data.drain(..self.start);
+            self.start = 0;
+        }
+
+        let decoded = self.start;
+        let data = &mut self.buffer.data;
+
+        while data.len() - decoded < required && !self.eof {
+            let end = data.len();
+            data.resize(end + (end - decoded).max(MIN_READ), 0);
+
+            match self.reader.read(&mut data[end..]) {
+                Ok(0) => {
+                    data.truncate(end);
+                    self.eof = true;
+                }
+                Ok(n) => data.truncate(end + n),
+                Err(err) => {
+                    data.truncate(end);
+                    if err.kind() != io::ErrorKind::Interrupted {
+                        return Err(err);
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+pub struct StreamIter<'a, R, T> {
+    reader: &'a mut StreamReader<R>,
+    failed: bool,
+    _marker: PhantomData<fn() -> T>,
+}
+
+impl<R: io::Read, T: DeSerializeOwned> Iterator for StreamIter<'_, R, T> {
+    type Item = Result<T, StreamError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.failed {
+            return None;
+        }
+
+        let result = self.reader.read().transpose();
+        self.failed = matches!(result, Some(Err(_)));
+        result
+    }
+}
@@ -0,0 +1,161 @@
+use crate::{
+    varint, Config, Cursor, DeSerialize, DeserializeError, Reader, RefTable, Serialize,
+    SizeCounter, Writer,
+};
+
+// Structs deriving with `#[sbs(tagged)]` are written as a varint field count
+// followed by each field as a varint id, a varint byte length and the field's
+// usual encoding. Readers skip fields with ids they don't know and fill in
+// defaults for fields that are missing, so fields can be added and removed
+// without breaking files written by older or newer code, as long as ids are
+// never reused.
+//
+// Each field is encoded on its own, so `Rc`/`Arc` sharing is not tracked
+// across, or within, tagged fields: a skipped field could otherwise hide the
+// value a later back-reference points to.
+
+/// Field-by-field access to a tagged struct, used by the derived impls.
+pub struct TaggedFields {
+    remaining: u64,
+}
+
+impl TaggedFields {
+    pub fn write_count<W: Writer + ?Sized>(count: u64, writer: &mut W) {
+        varint::write_unsigned(count as u128, writer)
+    }
+
+    pub fn write_field<T: Serialize + ?Sized, W: Writer + ?Sized>(
+        id: u32,
+        value: &T,
+        writer: &mut W,
+    ) {
+        let config = field_config(writer.config());
+        let len = SizeCounter::size_of(value, config);
+
+        varint::write_unsigned(id as u128, writer);
+        varint::write_unsigned(len as u128, writer);
+        value.serialize(&mut FieldWriter {
+            inner: writer,
+            config,
+            refs: RefTable::default(),
+        })
+    }
+
+    /// Reads the field count.
+    pub fn read<'de, R: Reader<'de> + ?Sized>(reader: &mut R) -> Result<Self, DeserializeError> {
+        let start = reader.position();
+        let remaining = u64::try_from(varint::read_unsigned(reader)?)
+            .map_err(|_| DeserializeError::InvalidVarint { offset: start })?;
+
+        Ok(Self { remaining })
+    }
+
+    /// Reads the next field, or returns `None` once all have been read.
+    pub fn next_field<'de, R: Reader<'de> + ?Sized>(
+        &mut self,
+        reader: &mut R,
+    ) -> Result<Option<TaggedField<'de>>, DeserializeError> {
+        if self.remaining == 0 {
+            return Ok(None);
+        }
+        self.remaining -= 1;
+
+        let start = reader.position();
+        let id = u32::try_from(varint::read_unsigned(reader)?)
+            .map_err(|_| DeserializeError::InvalidVarint { offset: start })?;
+
+        let len_start = reader.position();
+        let len = varint::read_unsigned(reader)?;
+        let len = match usize::try_from(len) {
+            Ok(len) if len <= reader.remaining() => len,
+            Ok(len) if len <= isize::MAX as usize => {
+                return Err(DeserializeError::LengthOverflow {
+                    offset: len_start,
+                    len: len as u64,
+                })
+            }
+            _ => {
+                return Err(DeserializeError::LengthTooLarge {
+                    offset: len_start,
+                    len: len.try_into().unwrap_or(u64::MAX),
+                })
+            }
+        };
+
+        let offset = reader.position();
+        Ok(Some(TaggedField {
+            id,
+            offset,
+            bytes: reader.read_bytes(len)?,
+            config: field_config(reader.config()),
+        }))
+    }
+}
+
+/// Writes a field's value straight into the enclosing writer, with the
+/// field's own config.
+struct FieldWriter<'a, W: ?Sized> {
+    inner: &'a mut W,
+    config: Config,
+    refs: RefTable,
+}
+
+impl<W: Writer + ?Sized> Writer for FieldWriter<'_, W> {
+    fn write_bytes(&mut self, bytes: &[u8]) {
+        self.inner.write_bytes(bytes)
+    }
+
+    fn config(&self) -> &Config {
+        &self.config
+    }
+
+    fn refs(&mut self) -> &mut RefTable {
+        &mut self.refs
+    }
+}
+
+fn field_config(config: &Config) -> Config {
+    Config {
+        track_refs: false,
+        reject_trailing_bytes: false,
+        ..*config
+    }
+}
+
+/// One field of a tagged struct, see `TaggedFields`.
+pub struct TaggedField<'de> {
+    id: u32,
+    offset: usize,
+    bytes: &'de [u8],
+    config: Config,
+}
+
+impl<'de> TaggedField<'de> {
+    pub fn id(&self) -> u32 {
+        self.id
+    }
+
+    /// Offset of the field's value in the enclosing buffer.
+    pub(crate) fn offset(&self) -> usize {
+        self.offset
+    }
+
+    /// Length of the field's value in bytes.
+    pub(crate) fn len(&self) -> usize {
+        self.bytes.len()
+    }
+
+    /// Cursor over just the field's value, with positions relative to its
+    /// start.
+    pub(crate) fn cursor(&self) -> Cursor<'de> {
+        Cursor::with_config(self.bytes, self.config)
+    }
+
+    /// Decodes the field's value. Bytes left over after it are ignored, so a
+    /// field may be decoded as an older, shorter layout of its type.
+    pub fn decode<T: DeSerialize<'de>>(&self) -> Result<T, DeserializeError> {
+        self.cursor()
+            .deserialize()
+            .map_err(|err| err.shifted(self.offset))
+    }
+}
@@ -0,0 +1,62 @@
+use crate::{DeserializeError, Reader, Writer};
+
+// a u128 needs at most 19 groups of 7 bits, the last holding only 2 of them
+const MAX_LEN: usize = 19;
+const LAST_BYTE_MAX: u8 = 0x03;
+
+/// Writes `value` as unsigned LEB128.
+pub(crate) fn write_unsigned<W: Writer + ?Sized>(value: u128, writer: &mut W) {
+    let mut buf = [0u8; MAX_LEN];
+    let mut value = value;
+    let mut len = 0;
+
+    loop {
+        let byte = (value & 0x7f) as u8;
+        value >>= 7;
+
+        if value == 0 {
+            buf[len] = byte;
+            break;
+        }
+
+        buf[len] = byte | 0x80;
+        len += 1;
+    }
+
+    writer.write_bytes(&buf[..=len])
+}
+
+/// Writes `value` zigzag encoded, so small negative numbers stay short.
+pub(crate) fn write_signed<W: Writer + ?Sized>(value: i128, writer: &mut W) {
+    write_unsigned(((value << 1) ^ (value >> 127)) as u128, writer)
+}
+
+pub(crate) fn read_unsigned<'de, R: Reader<'de> + ?Sized>(
+    reader: &mut R,
+) -> Result<u128, DeserializeError> {
+    let start = reader.position();
+    let mut value: u128 = 0;
+
+    for i in 0..MAX_LEN {
+        let byte = reader.read_bytes(1)?[0];
+
+        if i == MAX_LEN - 1 && byte > LAST_BYTE_MAX {
+            return Err(DeserializeError::InvalidVarint { offset: start });
+        }
+
+        value |= ((byte & 0x7f) as u128) << (7 * i);
+
+        if byte & 0x80 == 0 {
+            return Ok(value);
+        }
+    }
+
+    Err(DeserializeError::InvalidVarint { offset: start })
+}
+
+pub(crate) fn read_signed<'de, R: Reader<'de> + ?Sized>(
+    reader: &mut R,
+) -> Result<i128, DeserializeError> {
+    let value = read_unsigned(reader)?;
+    Ok((value >> 1) as i128 ^ -((value & 1) as i128))
+}
@@ -0,0 +1,111 @@
+use crate::{varint, DeSerialize, DeSerializeOwned, DeserializeError, Reader, Serialize, Writer};
+
+/// A type whose layout has changed over time, and that can still decode the
+/// older layouts by upgrading them one version at a time.
+///
+/// Every layout gets its own type: the current one implements `Migrate` with
+/// `Previous` set to the type of the layout before it, and so on down to the
+/// oldest layout, whose `Previous` is `NoPrevious`. Wrapping the value in
+/// `Versioned` writes `VERSION` in front of it, and reading a `Versioned<T>`
+/// decodes whichever layout the stored version names before passing it
+/// through each `migrate` step up to `T`.
+///
+/// ```ignore
+/// impl Migrate for SettingsV2 {
+///     const VERSION: u32 = 2;
+///     type Previous = SettingsV1;
+///
+///     fn migrate(old: SettingsV1) -> Self {
+///         SettingsV2 { name: old.name, retries: 3 }
+///     }
+/// }
+/// ```
+pub trait Migrate: DeSerializeOwned {
+    const VERSION: u32;
+
+    type Previous: Migrate;
+
+    fn migrate(previous: Self::Previous) -> Self;
+
+    /// Decodes the layout with the given `version` and upgrades it to `Self`.
+    /// `start` is where the version was read, for error reporting.
+    fn deserialize_version<'de, R: Reader<'de> + ?Sized>(
+        version: u32,
+        start: usize,
+        reader: &mut R,
+    ) -> Result<Self, DeserializeError> {
+        if version == Self::VERSION {
+            Self::deserialize(reader)
+        } else if version < Self::VERSION {
+            Self::Previous::deserialize_version(version, start, reader).map(Self::migrate)
+        } else {
+            Err(DeserializeError::UnsupportedVersion {
+                offset: start,
+                version,
+            })
+        }
+    }
+}
+
+/// `Migrate::Previous` of the oldest layout of a type. Any version older than
+/// that is rejected with `DeserializeError::UnsupportedVersion`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NoPrevious {}
+
+impl<'de> DeSerialize<'de> for NoPrevious {
+    fn deserialize<R: Reader<'de> + ?Sized>(reader: &mut R) -> Result<Self, DeserializeError> {
+        Err(DeserializeError::custom(
+            reader.position(),
+            "NoPrevious can't be decoded",
+        ))
+    }
+}
+
+impl Migrate for NoPrevious {
+    const VERSION: u32 = 0;
+
+    type Previous = NoPrevious;
+
+    fn migrate(previous: NoPrevious) -> Self {
+        previous
+    }
+
+    fn deserialize_version<'de, R: Reader<'de> + ?Sized>(
+        version: u32,
+        start: usize,
+        _reader: &mut R,
+    ) -> Result<Self, DeserializeError> {
+        Err(DeserializeError::UnsupportedVersion {
+            offset: start,
+            version,
+        })
+    }
+}
+
+/// Writes `T::VERSION` as a LEB128 varint in front of the value, and decodes
+/// any version of `T` that its `Migrate` chain knows about.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct Versioned<T>(pub T);
+
+impl<T> Versioned<T> {
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+impl<T: Migrate + Serialize> Serialize for Versioned<T> {
+    fn serialize<W: Writer + ?Sized>(&self, writer: &mut W) {
+        varint::write_unsigned(T::VERSION as u128, writer);
+        self.0.serialize(writer)
+    }
+}
+
+impl<'de, T: Migrate> DeSerialize<'de> for Versioned<T> {
+    fn deserialize<R: Reader<'de> + ?Sized>(reader: &mut R) -> Result<Self, DeserializeError> {
+        let start = reader.position();
+        let version = u32::try_from(varint::read_unsigned(reader)?)
+            .map_err(|_| DeserializeError::InvalidVarint { offset: start })?;
+
+        T::deserialize_version(version, start, reader).map(Versioned)
+    }
+}
@@ -0,0 +1,172 @@
+use std::{
+    borrow::Cow,
+    num::{
+        NonZeroI128, NonZeroI16, NonZeroI32, NonZeroI64, NonZeroI8, NonZeroIsize, NonZeroU128,
+        NonZeroU16, NonZeroU32, NonZeroU64, NonZeroU8, NonZeroUsize, Wrapping,
+    },
+    rc::Rc,
+    sync::Arc,
+    time::Duration,
+};
+
+use crate::{shared, DeSerialize, DeserializeError, Reader, Serialize, Writer};
+
+// Pointers and wrappers are transparent: they encode exactly like the value
+// they hold.
+
+impl<T: Serialize + ?Sized> Serialize for Box<T> {
+    fn serialize<W: Writer + ?Sized>(&self, writer: &mut W) {
+        (**self).serialize(writer)
+    }
+}
+
+impl<'de, T: DeSerialize<'de>> DeSerialize<'de> for Box<T> {
+    const MIN_SIZE: usize = T::MIN_SIZE;
+
+    fn deserialize<R: Reader<'de> + ?Sized>(reader: &mut R) -> Result<Self, DeserializeError> {
+        T::deserialize(reader).map(Box::new)
+    }
+}
+
+impl<'de> DeSerialize<'de> for Box<str> {
+    fn deserialize<R: Reader<'de> + ?Sized>(reader: &mut R) -> Result<Self, DeserializeError> {
+        String::deserialize(reader).map(Box::from)
+    }
+}
+
+impl<'de, T: DeSerialize<'de>> DeSerialize<'de> for Box<[T]> {
+    fn deserialize<R: Reader<'de> + ?Sized>(reader: &mut R) -> Result<Self, DeserializeError> {
+        Vec::<T>::deserialize(reader).map(Box::from)
+    }
+}
+
+// `Rc` and `Arc` additionally take part in reference tracking when
+// `Config::track_refs` is set, see `shared.rs`
+macro_rules! impl_serde_for_shared {
+    ($($p:ident),*) => {
+        $(
+            impl<T: Serialize + ?Sized> Serialize for $p<T> {
+                fn serialize<W: Writer + ?Sized>(&self, writer: &mut W) {
+                    let address = $p::as_ptr(self) as *const () as usize;
+                    shared::serialize_shared(address, writer, |writer| (**self).serialize(writer))
+                }
+            }
+
+            impl<'de, T: DeSerialize<'de> + 'static> DeSerialize<'de> for $p<T> {
+                // a back-reference is a single byte, however big the value
+                const MIN_SIZE: usize = if T::MIN_SIZE == 0 { 0 } else { 1 };
+
+                fn deserialize<R: Reader<'de> + ?Sized>(reader: &mut R) -> Result<Self, DeserializeError> {
+                    shared::deserialize_shared(reader, |reader| {
+                        T::deserialize(reader).map($p::new)
+                    })
+                }
+            }
+
+            impl<'de> DeSerialize<'de> for $p<str> {
+                fn deserialize<R: Reader<'de> + ?Sized>(reader: &mut R) -> Result<Self, DeserializeError> {
+                    shared::deserialize_shared(reader, |reader| {
+                        String::deserialize(reader).map($p::from)
+                    })
+                }
+            }
+
+            impl<'de, T: DeSerialize<'de> + 'static> DeSerialize<'de> for $p<[T]> {
+                fn deserialize<R: Reader<'de> + ?Sized>(reader: &mut R) -> Result<Self, DeserializeError> {
+                    shared::deserialize_shared(reader, |reader| {
+                        Vec::<T>::deserialize(reader).map($p::from)
+                    })
+                }
+            }
+        )*
+    };
+}
+
+impl_serde_for_shared!(Rc, Arc);
+
+impl<'de: 'a, 'a> DeSerialize<'de> for Cow<'a, [u8]> {
+    fn deserialize<R: Reader<'de> + ?Sized>(reader: &mut R) -> Result<Self, DeserializeError> {
+        <&[u8]>::deserialize(reader).map(Cow::Borrowed)
+    }
+}
+
+impl<T: Serialize> Serialize for Wrapping<T> {
+    fn serialize<W: Writer + ?Sized>(&self, writer: &mut W) {
+        self.0.serialize(writer)
+    }
+}
+
+impl<'de, T: DeSerialize<'de>> DeSerialize<'de> for Wrapping<T> {
+    const MIN_SIZE: usize = T::MIN_SIZE;
+
+    fn deserialize<R: Reader<'de> + ?Sized>(reader: &mut R) -> Result<Self, DeserializeError> {
+        T::deserialize(reader).map(Wrapping)
+    }
+}
+
+macro_rules! impl_serde_for_nonzero {
+    ($($t:ident => $inner:ty),*) => {
+        $(
+            impl Serialize for $t {
+                fn serialize<W: Writer + ?Sized>(&self, writer: &mut W) {
+                    self.get().serialize(writer)
+                }
+            }
+
+            impl<'de> DeSerialize<'de> for $t {
+                fn deserialize<R: Reader<'de> + ?Sized>(reader: &mut R) -> Result<Self, DeserializeError> {
+                    let start = reader.position();
+                    let value = <$inner>::deserialize(reader)?;
+
+                    $t::new(value).ok_or(DeserializeError::OutOfRange {
+                        offset: start,
+                        ty: stringify!($t),
+                    })
+                }
+            }
+        )*
+    };
+}
+
+impl_serde_for_nonzero!(
+    NonZeroI8 => i8,
+    NonZeroI16 => i16,
+    NonZeroI32 => i32,
+    NonZeroI64 => i64,
+    NonZeroI128 => i128,
+    NonZeroIsize => isize,
+    NonZeroU8 => u8,
+    NonZeroU16 => u16,
+    NonZeroU32 => u32,
+    NonZeroU64 => u64,
+    NonZeroU128 => u128,
+    NonZeroUsize => usize
+);
+
+const NANOS_PER_SEC: u32 = 1_000_000_000;
+
+/// Written as whole seconds (`u64`) followed by the sub-second nanoseconds
+/// (`u32`).
+impl Serialize for Duration {
+    fn serialize<W: Writer + ?Sized>(&self, writer: &mut W) {
+        self.as_secs().serialize(writer);
+        self.subsec_nanos().serialize(writer);
+    }
+}
+
+impl<'de> DeSerialize<'de> for Duration {
+    fn deserialize<R: Reader<'de> + ?Sized>(reader: &mut R) -> Result<Self, DeserializeError> {
+        let secs = u64::deserialize(reader)?;
+        let start = reader.position();
+        let nanos = u32::deserialize(reader)?;
+
+        if nanos >= NANOS_PER_SEC {
+            return Err(DeserializeError::OutOfRange {
+                offset: start,
+                ty: "Duration",
+            });
+        }
+
+        Ok(Duration::new(secs, nanos))
+    }
+}
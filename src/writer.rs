@@ -0,0 +1,174 @@
+use std::{hash::Hasher, io};
+
+use crate::{Config, RefTable, Serialize, Writer};
+
+/// Serializes straight into an `io::Write` instead of an in-memory buffer.
+///
+/// Every `write_bytes` call is passed on to the underlying writer as is, so
+/// wrap unbuffered destinations like a `File` or `TcpStream` in a
+/// `BufWriter`. The first I/O error stops all further writes and is returned
+/// from `serialize`. An `IoWriter<&mut [u8]>` writes into a fixed-size
+/// buffer, failing with `ErrorKind::WriteZero` once it is full.
+pub struct IoWriter<W> {
+    inner: W,
+    config: Config,
+    refs: RefTable,
+    written: u64,
+    error: Option<io::Error>,
+}
+
+impl<W: io::Write> IoWriter<W> {
+    pub fn new(inner: W) -> Self {
+        Self::with_config(inner, Config::default())
+    }
+
+    pub fn with_config(inner: W, config: Config) -> Self {
+        Self {
+            inner,
+            config,
+            refs: RefTable::default(),
+            written: 0,
+            error: None,
+        }
+    }
+
+    pub fn serialize<T: Serialize>(&mut self, root: T) -> io::Result<()> {
+        self.refs.clear();
+        root.serialize(self);
+
+        match self.error.take() {
+            Some(err) => Err(err),
+            None => Ok(()),
+        }
+    }
+
+    /// Total number of bytes written so far.
+    pub fn written(&self) -> u64 {
+        self.written
+    }
+
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+
+    pub fn get_ref(&self) -> &W {
+        &self.inner
+    }
+
+    pub fn into_inner(self) -> W {
+        self.inner
+    }
+}
+
+impl<W: io::Write> Writer for IoWriter<W> {
+    fn write_bytes(&mut self, bytes: &[u8]) {
+        if self.error.is_some() {
+            return;
+        }
+
+        match self.inner.write_all(bytes) {
+            Ok(()) => self.written += bytes.len() as u64,
+            Err(err) => self.error = Some(err),
+        }
+    }
+
+    fn config(&self) -> &Config {
+        &self.config
+    }
+
+    fn refs(&mut self) -> &mut RefTable {
+        &mut self.refs
+    }
+}
+
+/// Counts the bytes a value would serialize to without storing them.
+pub struct SizeCounter {
+    config: Config,
+    refs: RefTable,
+    size: u64,
+}
+
+impl SizeCounter {
+    pub fn new(config: Config) -> Self {
+        Self {
+            config,
+            refs: RefTable::default(),
+            size: 0,
+        }
+    }
+
+    /// Size of `root` when serialized on its own with `config`.
+    pub fn size_of<T: Serialize>(root: T, config: Config) -> u64 {
+        let mut counter = Self::new(config);
+        counter.serialize(root);
+        counter.size()
+    }
+
+    pub fn serialize<T: Serialize>(&mut self, root: T) {
+        self.refs.clear();
+        root.serialize(self)
+    }
+
+    pub fn size(&self) -> u64 {
+        self.size
+    }
+}
+
+impl Writer for SizeCounter {
+    fn write_bytes(&mut self, bytes: &[u8]) {
+        self.size += bytes.len() as u64
+    }
+
+    fn config(&self) -> &Config {
+        &self.config
+    }
+
+    fn refs(&mut self) -> &mut RefTable {
+        &mut self.refs
+    }
+}
+
+/// Feeds the serialized bytes of values into a `Hasher`, for fingerprinting
+/// values by their encoding without buffering it.
+pub struct HashWriter<H> {
+    hasher: H,
+    config: Config,
+    refs: RefTable,
+}
+
+impl<H: Hasher> HashWriter<H> {
+    pub fn new(hasher: H, config: Config) -> Self {
+        Self {
+            hasher,
+            config,
+            refs: RefTable::default(),
+        }
+    }
+
+    pub fn serialize<T: Serialize>(&mut self, root: T) {
+        self.refs.clear();
+        root.serialize(self)
+    }
+
+    pub fn finish(&self) -> u64 {
+        self.hasher.finish()
+    }
+
+    pub fn into_inner(self) -> H {
+        self.hasher
+    }
+}
+
+impl<H: Hasher> Writer for HashWriter<H> {
+    fn write_bytes(&mut self, bytes: &[u8]) {
+        self.hasher.write(bytes)
+    }
+
+    fn config(&self) -> &Config {
+        &self.config
+    }
+
+    fn refs(&mut self) -> &mut RefTable {
+        &mut self.refs
+    }
+}
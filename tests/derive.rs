@@ -0,0 +1,222 @@
+use std::fmt::Debug;
+
+use sbs_api_internal::{
+    DeSerialize, DeSerializeOwned, Describe, DeserializeError, Schema, Serialize, SBI,
+};
+
+#[derive(Serialize, DeSerialize, Describe, Debug, PartialEq)]
+struct Named {
+    id: u32,
+    name: String,
+    tags: Vec<u8>,
+}
+
+#[derive(Serialize, DeSerialize, Describe, Debug, PartialEq)]
+struct Tuple(u16, u8);
+
+#[derive(Serialize, DeSerialize, Describe, Debug, PartialEq)]
+struct Unit;
+
+#[derive(Serialize, DeSerialize, Describe, Debug, PartialEq)]
+struct Generic<T, U> {
+    first: T,
+    rest: Vec<U>,
+}
+
+#[derive(Serialize, DeSerialize, Debug, PartialEq)]
+struct Borrowed<'a, 'b> {
+    name: &'a str,
+    bytes: &'b [u8],
+}
+
+#[derive(Serialize, DeSerialize, Describe, Debug)]
+enum Never {}
+
+// the generated code must not pick up a local `Ok` or `Err`
+#[allow(dead_code, unused_imports)]
+mod shadowed {
+    use sbs_api_internal::{DeSerialize, Serialize};
+
+    enum Shadowed {
+        Ok,
+        Err,
+    }
+    use Shadowed::{Err, Ok};
+
+    #[derive(Serialize, DeSerialize)]
+    struct Plain(u8);
+
+    #[derive(Serialize, DeSerialize)]
+    #[sbs(tagged)]
+    struct Tagged {
+        #[sbs(id = 1)]
+        value: u8,
+    }
+
+    #[derive(Serialize, DeSerialize)]
+    enum Choice {
+        A,
+        B(u8),
+    }
+}
+
+#[derive(Serialize, DeSerialize, Debug, PartialEq)]
+enum Shape {
+    Point,
+    Circle(u8),
+    Rect { width: u16, height: u16 },
+}
+
+#[derive(Serialize, DeSerialize, Debug, PartialEq, Clone, Copy)]
+enum Level {
+    Low,
+    High = 10,
+    Higher,
+    Max = 300,
+}
+
+#[derive(Serialize, DeSerialize, Describe, Debug, PartialEq)]
+#[repr(i8)]
+enum Sign {
+    Negative = -1,
+    Zero,
+    Positive,
+}
+
+fn round_trip<T: Serialize + DeSerializeOwned + PartialEq + Debug>(value: T) -> Vec<u8> {
+    let mut sbi = SBI::new();
+    sbi.serialize(&value);
+    assert_eq!(sbi.deserialize::<T>().unwrap(), value);
+    sbi.data
+}
+
+#[test]
+fn structs_encode_their_fields_in_order() {
+    assert_eq!(
+        round_trip(Named {
+            id: 7,
+            name: "ab".into(),
+            tags: vec![1],
+        }),
+        [0, 0, 0, 7, 0, 0, 0, 0, 0, 0, 0, 2, b'a', b'b', 0, 0, 0, 0, 0, 0, 0, 1, 1]
+    );
+    assert_eq!(round_trip(Tuple(0x0102, 3)), [1, 2, 3]);
+    assert_eq!(round_trip(Unit), []);
+    assert_eq!(round_trip(vec![Unit, Unit, Unit]), [0, 0, 0, 0, 0, 0, 0, 3]);
+    assert_eq!(
+        round_trip(Generic {
+            first: Tuple(3, 4),
+            rest: vec![Unit],
+        }),
+        [0, 3, 4, 0, 0, 0, 0, 0, 0, 0, 1]
+    );
+    round_trip(vec![
+        Shape::Point,
+        Shape::Circle(4),
+        Shape::Rect {
+            width: 2,
+            height: 3,
+        },
+    ]);
+}
+
+#[test]
+fn structs_with_lifetimes_borrow_from_the_buffer() {
+    let mut sbi = SBI::new();
+    sbi.serialize(Borrowed {
+        name: "hi",
+        bytes: &[1, 2],
+    });
+
+    let decoded = sbi.deserialize::<Borrowed>().unwrap();
+    assert_eq!(
+        decoded,
+        Borrowed {
+            name: "hi",
+            bytes: &[1, 2],
+        }
+    );
+    assert!(sbi.data.as_ptr_range().contains(&decoded.name.as_ptr()));
+    assert!(sbi.data.as_ptr_range().contains(&decoded.bytes.as_ptr()));
+}
+
+#[test]
+fn enums_without_variants_decode_to_an_error() {
+    let mut sbi = SBI::new();
+    sbi.serialize(0u8);
+    assert!(sbi.deserialize::<Never>().is_err());
+    assert_eq!(
+        Never::schema(),
+        Schema::Enum {
+            name: "Never".into(),
+            variants: vec![],
+        }
+    );
+}
+
+#[test]
+fn enums_encode_a_varint_discriminant_before_their_fields() {
+    assert_eq!(round_trip(Shape::Point), [0]);
+    assert_eq!(round_trip(Shape::Circle(4)), [1, 4]);
+    assert_eq!(
+        round_trip(Shape::Rect {
+            width: 2,
+            height: 0x0304,
+        }),
+        [2, 0, 2, 3, 4]
+    );
+}
+
+#[test]
+fn implicit_discriminants_follow_the_previous_one() {
+    assert_eq!(round_trip(Level::Low), [0]);
+    assert_eq!(round_trip(Level::High), [10]);
+    assert_eq!(round_trip(Level::Higher), [11]);
+    assert_eq!(round_trip(Level::Max), [0xac, 0x02]);
+}
+
+#[test]
+fn negative_discriminants_wrap_to_64_bits() {
+    // u64::MAX as a varint
+    assert_eq!(
+        round_trip(Sign::Negative),
+        [0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x01]
+    );
+    assert_eq!(round_trip(Sign::Zero), [0]);
+    assert_eq!(round_trip(Sign::Positive), [1]);
+
+    let Schema::Enum { variants, .. } = Sign::schema() else {
+        panic!("not an enum schema");
+    };
+    let discriminants: Vec<_> = variants
+        .iter()
+        .map(|variant| variant.discriminant)
+        .collect();
+    assert_eq!(discriminants, [u64::MAX, 0, 1]);
+}
+
+#[test]
+fn unknown_discriminants_are_rejected() {
+    let mut sbi = SBI::new();
+    sbi.serialize(Level::High);
+    sbi.data[0] = 1;
+    assert_eq!(
+        sbi.deserialize::<Level>(),
+        Err(DeserializeError::InvalidDiscriminant {
+            offset: 0,
+            discriminant: 1,
+        })
+    );
+
+    // the offset points at the discriminant, not the start of the buffer
+    let mut sbi = SBI::new();
+    sbi.serialize(vec![Shape::Point]);
+    sbi.data[8] = 3;
+    assert_eq!(
+        sbi.deserialize::<Vec<Shape>>(),
+        Err(DeserializeError::InvalidDiscriminant {
+            offset: 8,
+            discriminant: 3,
+        })
+    );
+}
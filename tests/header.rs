@@ -0,0 +1,135 @@
+use std::{fs, path::PathBuf, process};
+
+use sbs_api_internal::{ByteOrder, Config, Header, HeaderError, IntEncoding, SBI};
+
+const MAGIC: [u8; 4] = *b"EVLG";
+
+fn temp_path(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("sbs-header-{}-{name}", process::id()))
+}
+
+fn with_header(version: u16) -> Config {
+    Config {
+        header: Some(Header::new(MAGIC, version)),
+        ..Config::default()
+    }
+}
+
+fn load(path: &PathBuf, config: Config) -> Result<SBI, HeaderError> {
+    SBI::from_path_with_config(path, config).map_err(|err| *err.downcast::<HeaderError>().unwrap())
+}
+
+#[test]
+fn header_records_the_encoding() {
+    let path = temp_path("encoding");
+    let mut sbi = SBI::with_config(Config {
+        int_encoding: IntEncoding::Varint,
+        byte_order: ByteOrder::Little,
+        ..with_header(3)
+    });
+    sbi.serialize((5u64, 1.5f32, "payload"));
+    sbi.write_to_path(&path).unwrap();
+
+    let file = fs::read(&path).unwrap();
+    assert_eq!(file[..Header::LEN], [b'E', b'V', b'L', b'G', 0, 3, 0b0101]);
+
+    let loaded = load(&path, with_header(3)).unwrap();
+    fs::remove_file(&path).unwrap();
+
+    assert_eq!(loaded.data, sbi.data);
+    assert_eq!(loaded.config, sbi.config);
+    assert_eq!(
+        loaded.deserialize::<(u64, f32, String)>().unwrap(),
+        (5, 1.5, "payload".into())
+    );
+}
+
+#[test]
+fn wrong_magic_and_newer_versions_are_rejected() {
+    let path = temp_path("rejected");
+    let mut sbi = SBI::with_config(with_header(2));
+    sbi.serialize(1u8);
+    sbi.write_to_path(&path).unwrap();
+
+    let other = Config {
+        header: Some(Header::new(*b"OTHR", 2)),
+        ..Config::default()
+    };
+    let wrong_magic = load(&path, other);
+    let too_new = load(&path, with_header(1));
+    let older = load(&path, with_header(4)).unwrap();
+
+    fs::write(&path, b"EVL").unwrap();
+    let truncated = load(&path, with_header(2));
+    fs::remove_file(&path).unwrap();
+
+    assert_eq!(
+        wrong_magic.err().unwrap(),
+        HeaderError::WrongMagic {
+            expected: *b"OTHR",
+            found: MAGIC
+        }
+    );
+    assert_eq!(
+        too_new.err().unwrap(),
+        HeaderError::UnsupportedVersion {
+            version: 2,
+            supported: 1
+        }
+    );
+    assert_eq!(older.config.header, Some(Header::new(MAGIC, 2)));
+    assert_eq!(truncated.err().unwrap(), HeaderError::Truncated { len: 3 });
+}
+
+#[test]
+fn files_without_a_header_are_raw_payloads() {
+    let path = temp_path("raw");
+    let mut sbi = SBI::new();
+    sbi.serialize(0x0102u16);
+    sbi.write_to_path(&path).unwrap();
+
+    assert_eq!(fs::read(&path).unwrap(), [1, 2]);
+    assert_eq!(
+        SBI::from_path(&path).unwrap().deserialize::<u16>().unwrap(),
+        0x0102
+    );
+    fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn native_byte_order_is_recorded_as_the_hosts_order() {
+    let native = Config {
+        byte_order: ByteOrder::Native,
+        ..with_header(1)
+    };
+    let host = if cfg!(target_endian = "big") {
+        ByteOrder::Big
+    } else {
+        ByteOrder::Little
+    };
+
+    let header = Header::new(MAGIC, 1);
+    let encoded = header.encode(&native);
+    assert_eq!(
+        encoded,
+        header.encode(&Config {
+            byte_order: host,
+            ..native
+        })
+    );
+
+    let mut file = encoded.to_vec();
+    file.extend(0x0102u16.to_ne_bytes());
+    let decoded = header.decode(&file, with_header(1)).unwrap();
+    assert_eq!(decoded.byte_order, host);
+
+    let mut sbi = SBI::with_config(decoded);
+    sbi.data = file[Header::LEN..].to_vec();
+    assert_eq!(sbi.deserialize::<u16>().unwrap(), 0x0102);
+
+    file[6] = 2;
+    assert_eq!(
+        header.decode(&file, with_header(1)),
+        Err(HeaderError::InvalidFlags { flags: 2 })
+    );
+}
@@ -0,0 +1,381 @@
+use std::{num::NonZeroU32, rc::Rc, time::Duration};
+
+use sbs_api_internal::{
+    ByteSource, Config, DeSerializeOwned, DeserializeError, Schema, Serialize, SBI,
+};
+
+fn encode<T: Serialize>(value: T) -> Vec<u8> {
+    let mut sbi = SBI::new();
+    sbi.serialize(value);
+    sbi.data
+}
+
+fn decode<T: DeSerializeOwned>(data: &[u8]) -> Result<T, DeserializeError> {
+    let mut sbi = SBI::new();
+    sbi.data = data.to_vec();
+    sbi.deserialize()
+}
+
+// xorshift64*, so the inputs are reproducible without pulling in a rng crate
+struct Rng(u64);
+
+impl Rng {
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        self.0.wrapping_mul(0x2545_f491_4f6c_dd1d)
+    }
+
+    fn bytes(&mut self, len: usize) -> Vec<u8> {
+        (0..len).map(|_| self.next() as u8).collect()
+    }
+}
+
+#[test]
+fn truncated_values_report_where_the_data_ran_out() {
+    assert_eq!(
+        decode::<u32>(&[1, 2]),
+        Err(DeserializeError::UnexpectedEof {
+            offset: 0,
+            needed: 4
+        })
+    );
+
+    let data = encode(vec![1u16, 2]);
+    assert_eq!(decode::<Vec<u16>>(&data), Ok(vec![1, 2]));
+    assert_eq!(
+        decode::<Vec<u16>>(&data[..11]),
+        Err(DeserializeError::UnexpectedEof {
+            offset: 10,
+            needed: 2
+        })
+    );
+}
+
+#[test]
+fn errors_describe_what_went_wrong_and_where() {
+    let err = decode::<Vec<u16>>(&[0; 5]).unwrap_err();
+    assert_eq!(err.offset(), 0);
+    assert_eq!(
+        err.to_string(),
+        "unexpected end of data at offset 0, needed 8 bytes"
+    );
+
+    let err = DeserializeError::custom(3, "checksum mismatch");
+    assert_eq!(err.offset(), 3);
+    assert_eq!(err.to_string(), "checksum mismatch at offset 3");
+
+    let err: Box<dyn std::error::Error> = err.into();
+    assert_eq!(err.to_string(), "checksum mismatch at offset 3");
+}
+
+#[test]
+fn truncated_inputs_error_instead_of_panicking() {
+    let data = encode(vec![
+        String::from("hello"),
+        String::new(),
+        String::from("wörld"),
+    ]);
+    assert!(decode::<Vec<String>>(&data).is_ok());
+
+    for len in 0..data.len() {
+        let err = decode::<Vec<String>>(&data[..len]).unwrap_err();
+        assert!(err.offset() <= len, "{err} for truncation at {len}");
+    }
+
+    let data = encode(vec![vec![1u32, 2, 3], vec![4]]);
+    for len in 0..data.len() {
+        assert!(decode::<Vec<Vec<u32>>>(&data[..len]).is_err());
+    }
+}
+
+#[test]
+fn string_length_past_end_is_rejected() {
+    let mut data = encode(String::from("abc"));
+    data[..8].copy_from_slice(&4u64.to_be_bytes());
+
+    assert_eq!(
+        decode::<String>(&data),
+        Err(DeserializeError::LengthOverflow { offset: 0, len: 4 })
+    );
+}
+
+#[test]
+fn huge_length_prefixes_do_not_allocate() {
+    // longer than the data, but a longer input could hold them
+    for len in [1u64 << 20, 1 << 28] {
+        let data = len.to_be_bytes();
+
+        assert_eq!(
+            decode::<String>(&data),
+            Err(DeserializeError::LengthOverflow { offset: 0, len })
+        );
+        assert_eq!(
+            decode::<Vec<u32>>(&data),
+            Err(DeserializeError::LengthOverflow { offset: 0, len })
+        );
+        assert!(decode::<Vec<Vec<u8>>>(&data).is_err());
+    }
+
+    // longer than any buffer can be
+    for len in [u64::MAX, u64::MAX / 2 + 1] {
+        let data = len.to_be_bytes();
+
+        assert_eq!(
+            decode::<String>(&data),
+            Err(DeserializeError::LengthTooLarge { offset: 0, len })
+        );
+        assert_eq!(
+            decode::<Vec<u64>>(&data),
+            Err(DeserializeError::LengthTooLarge { offset: 0, len })
+        );
+        assert!(decode::<Vec<Vec<u8>>>(&data).is_err());
+    }
+    assert_eq!(
+        decode::<Vec<[u8; 8]>>(&(u64::MAX / 4).to_be_bytes()),
+        Err(DeserializeError::LengthTooLarge {
+            offset: 0,
+            len: u64::MAX / 4
+        })
+    );
+}
+
+#[test]
+fn nested_length_prefix_is_checked_against_remaining_bytes() {
+    let mut data = encode(vec![vec![7u8; 4]]);
+    // inner vec claims more elements than the outer buffer holds
+    data[8..16].copy_from_slice(&5u64.to_be_bytes());
+
+    assert_eq!(
+        decode::<Vec<Vec<u8>>>(&data),
+        Err(DeserializeError::LengthOverflow { offset: 8, len: 5 })
+    );
+}
+
+#[test]
+fn random_inputs_never_panic() {
+    let mut rng = Rng(0x5eed_1234_abcd_0001);
+
+    for _ in 0..2000 {
+        let len = (rng.next() % 64) as usize;
+        let data = rng.bytes(len);
+
+        let _ = decode::<String>(&data);
+        let _ = decode::<Vec<String>>(&data);
+        let _ = decode::<Vec<Vec<u16>>>(&data);
+        let _ = decode::<Vec<i128>>(&data);
+    }
+}
+
+#[test]
+fn random_corruption_of_valid_input_never_panics() {
+    let mut rng = Rng(0xdead_beef_f00d_0042);
+    let data = encode(vec![
+        vec![String::from("first"), String::from("second")],
+        vec![],
+        vec![String::from("third")],
+    ]);
+
+    for _ in 0..2000 {
+        let mut corrupted = data.clone();
+        for _ in 0..1 + rng.next() % 4 {
+            let index = (rng.next() as usize) % corrupted.len();
+            corrupted[index] = rng.next() as u8;
+        }
+
+        let _ = decode::<Vec<Vec<String>>>(&corrupted);
+    }
+}
+
+#[test]
+fn invalid_utf8_is_rejected_unless_lossy() {
+    let mut data = encode(String::from("ab\u{e9}d"));
+    // drop the continuation byte of 'é', leaving a lone lead byte
+    data[8 + 3] = b'x';
+
+    assert_eq!(
+        decode::<String>(&data),
+        Err(DeserializeError::InvalidUtf8 { offset: 8 + 2 })
+    );
+
+    let mut sbi = SBI::with_config(Config {
+        lossy_utf8: true,
+        ..Config::default()
+    });
+    sbi.data = data;
+    assert_eq!(sbi.deserialize::<String>().unwrap(), "ab\u{fffd}xd");
+}
+
+#[test]
+fn invalid_tags_and_scalars_are_rejected() {
+    assert_eq!(
+        decode::<bool>(&[2]),
+        Err(DeserializeError::InvalidTag { offset: 0, tag: 2 })
+    );
+    assert_eq!(
+        decode::<Option<u8>>(&[3, 0]),
+        Err(DeserializeError::InvalidTag { offset: 0, tag: 3 })
+    );
+    assert_eq!(
+        decode::<char>(&0xd800u32.to_be_bytes()),
+        Err(DeserializeError::InvalidChar {
+            offset: 0,
+            value: 0xd800
+        })
+    );
+    assert!(decode::<[u16; 3]>(&[0, 1, 0, 2, 0]).is_err());
+}
+
+#[test]
+fn zero_non_zero_and_oversized_nanos_are_rejected() {
+    assert_eq!(
+        decode::<NonZeroU32>(&[0; 4]),
+        Err(DeserializeError::OutOfRange {
+            offset: 0,
+            ty: "NonZeroU32"
+        })
+    );
+
+    let mut data = encode(Duration::new(1, 0));
+    data[8..].copy_from_slice(&1_000_000_000u32.to_be_bytes());
+    assert_eq!(
+        decode::<Duration>(&data),
+        Err(DeserializeError::OutOfRange {
+            offset: 8,
+            ty: "Duration"
+        })
+    );
+}
+
+#[test]
+fn dangling_back_references_are_rejected() {
+    let mut sbi = SBI::with_config(Config {
+        track_refs: true,
+        ..Config::default()
+    });
+    // length 2, the first element inline, the second pointing at id 1
+    sbi.data = vec![0, 0, 0, 0, 0, 0, 0, 2, 0, 9, 2];
+
+    assert_eq!(
+        sbi.deserialize::<Vec<Rc<u8>>>(),
+        Err(DeserializeError::InvalidReference { offset: 10, id: 1 })
+    );
+
+    // a back-reference to a value of another type
+    sbi.data = vec![0, 9, 1];
+    assert_eq!(
+        sbi.deserialize::<(Rc<u8>, Rc<u16>)>(),
+        Err(DeserializeError::InvalidReference { offset: 2, id: 0 })
+    );
+}
+
+#[test]
+fn trailing_bytes_are_rejected_in_strict_mode() {
+    let mut sbi = SBI::new();
+    sbi.serialize(7u16);
+    sbi.data.push(0);
+    assert_eq!(sbi.deserialize::<u16>().unwrap(), 7);
+
+    sbi.config.reject_trailing_bytes = true;
+    assert_eq!(
+        sbi.deserialize::<u16>(),
+        Err(DeserializeError::TrailingBytes { offset: 2, len: 1 })
+    );
+
+    sbi.data.pop();
+    assert_eq!(sbi.deserialize::<u16>().unwrap(), 7);
+}
+
+#[test]
+fn iteration_stops_at_a_truncated_record() {
+    let mut sbi = SBI::new();
+    for event in ["start", "tick", "stop"] {
+        sbi.serialize(event);
+    }
+    sbi.data.truncate(sbi.data.len() - 1);
+
+    let events: Vec<_> = sbi.iter::<&str>().collect();
+    assert_eq!(events[..2], [Ok("start"), Ok("tick")]);
+    assert_eq!(events[2..], [Err(DeserializeError::LengthOverflow { offset: 25, len: 4 })]);
+}
+
+#[test]
+fn iteration_over_empty_values_stops_at_data_it_cannot_consume() {
+    let mut sbi = SBI::new();
+    sbi.serialize(7u8);
+
+    let values: Vec<_> = sbi.iter::<()>().collect();
+    assert_eq!(
+        values,
+        [Err(DeserializeError::TrailingBytes { offset: 0, len: 1 })]
+    );
+    let values: Vec<_> = sbi.iter::<[u8; 0]>().collect();
+    assert_eq!(
+        values,
+        [Err(DeserializeError::TrailingBytes { offset: 0, len: 1 })]
+    );
+
+    sbi.data.clear();
+    assert_eq!(sbi.iter::<()>().count(), 0);
+}
+
+#[test]
+fn deeply_nested_schemas_are_rejected() {
+    // every byte starts another `Schema::Option`
+    let error = ByteSource::new(vec![16u8; 1 << 20])
+        .deserialize::<Schema>()
+        .unwrap_err();
+    assert_eq!(
+        error,
+        DeserializeError::TooDeep {
+            offset: 128,
+            limit: 128
+        }
+    );
+
+    let mut nested = Schema::Unit;
+    for _ in 0..127 {
+        nested = Schema::Seq(Box::new(nested));
+    }
+    assert_eq!(decode::<Schema>(&encode(&nested)), Ok(nested));
+}
+
+#[test]
+fn lengths_of_empty_elements_are_bounded() {
+    let data = encode(u64::MAX);
+    assert_eq!(
+        decode::<Vec<()>>(&data),
+        Err(DeserializeError::LengthTooLarge {
+            offset: 0,
+            len: u64::MAX
+        })
+    );
+    let mismatch = Schema::Seq(Box::new(Schema::Unit))
+        .validate_bytes(&data, Config::default())
+        .unwrap_err();
+    assert_eq!(mismatch.offset(), 0);
+
+    // arrays take their length from the schema rather than the data
+    for len in [1 << 26, 1 << 63] {
+        let mismatch = Schema::Array(Box::new(Schema::Unit), len)
+            .validate_bytes(&[], Config::default())
+            .unwrap_err();
+        assert_eq!(
+            mismatch.error,
+            DeserializeError::LengthTooLarge { offset: 0, len }
+        );
+    }
+    let mismatch = Schema::Array(Box::new(Schema::U32), 1 << 63)
+        .validate_bytes(&data, Config::default())
+        .unwrap_err();
+    assert_eq!(mismatch.offset(), 0);
+    assert!(Schema::Array(Box::new(Schema::Unit), 3)
+        .validate_bytes(&[], Config::default())
+        .is_ok());
+
+    let boxed = vec![Box::new(()), Box::new(())];
+    assert_eq!(decode::<Vec<Box<()>>>(&encode(&boxed)), Ok(boxed));
+    let pairs = vec![((), [(); 3]); 5];
+    assert_eq!(decode::<Vec<((), [(); 3])>>(&encode(&pairs)), Ok(pairs));
+}
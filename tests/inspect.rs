@@ -0,0 +1,215 @@
+use std::{
+    fs,
+    path::PathBuf,
+    process::{self, Command, Output},
+};
+
+use sbs_api_internal::{Config, Describe, Field, Header, IntEncoding, Schema, Serialize, SBI};
+
+#[derive(Serialize, Describe)]
+struct Save {
+    name: String,
+    level: u16,
+    inventory: Vec<Item>,
+}
+
+#[derive(Serialize, Describe)]
+struct Item {
+    id: u32,
+    count: u8,
+}
+
+fn temp_path(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("sbs-inspect-{}-{name}", process::id()))
+}
+
+fn config() -> Config {
+    Config {
+        int_encoding: IntEncoding::Varint,
+        header: Some(Header::new(*b"SAVE", 2)),
+        ..Config::default()
+    }
+}
+
+fn save(level: u16, counts: &[u8]) -> Save {
+    Save {
+        name: "hero".into(),
+        level,
+        inventory: counts
+            .iter()
+            .enumerate()
+            .map(|(id, &count)| Item {
+                id: id as u32,
+                count,
+            })
+            .collect(),
+    }
+}
+
+/// Writes the values and the schema of `Save`, returning their paths.
+fn write_files(name: &str, saves: &[Save]) -> (PathBuf, Vec<PathBuf>) {
+    let schema = temp_path(&format!("{name}.schema"));
+    let mut sbi = SBI::new();
+    sbi.serialize(Save::schema());
+    sbi.write_to_path(&schema).unwrap();
+
+    let paths = saves
+        .iter()
+        .enumerate()
+        .map(|(i, save)| {
+            let path = temp_path(&format!("{name}-{i}.sav"));
+            let mut sbi = SBI::with_config(config());
+            sbi.serialize(save);
+            sbi.write_to_path(&path).unwrap();
+            path
+        })
+        .collect();
+
+    (schema, paths)
+}
+
+fn inspect(args: &[&str], paths: &[&PathBuf]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_sbs-inspect"))
+        .args(args)
+        .args(paths)
+        .output()
+        .unwrap()
+}
+
+fn stdout(output: &Output) -> &str {
+    std::str::from_utf8(&output.stdout).unwrap()
+}
+
+#[test]
+fn shows_the_header_and_decoded_values() {
+    let (schema, paths) = write_files("decode", &[save(7, &[3])]);
+
+    let output = inspect(&["header"], &[&paths[0]]);
+    assert!(output.status.success());
+    assert_eq!(
+        stdout(&output),
+        "\
+magic       SAVE ([53, 41, 56, 45])
+version     2
+byte order  Big
+integers    Varint
+track refs  false
+body        9 bytes
+"
+    );
+
+    let output = inspect(&["decode", "--header", "--schema"], &[&schema, &paths[0]]);
+    assert!(output.status.success());
+    assert_eq!(
+        stdout(&output),
+        "\
+00000000  Save: struct Save
+00000000    name: string = \"hero\"
+00000005    level: u16 = 7
+00000006    inventory: seq of 1
+00000007      [0]: struct Item
+00000007        id: u32 = 0
+00000008        count: u8 = 3
+"
+    );
+
+    let output = inspect(
+        &["hex", "--magic", "SAVE", "--schema"],
+        &[&schema, &paths[0]],
+    );
+    assert!(output.status.success());
+    assert!(stdout(&output).starts_with(
+        "00000000                                                   Save: struct Save\n\
+         00000000  04 68 65 72 6f                                     name: string = \"hero\"\n"
+    ));
+
+    fs::remove_file(&schema).unwrap();
+    fs::remove_file(&paths[0]).unwrap();
+}
+
+#[test]
+fn marks_where_decoding_fails() {
+    let (schema, paths) = write_files("broken", &[save(7, &[3, 4])]);
+
+    // claim a third item that isn't there
+    let mut file = fs::read(&paths[0]).unwrap();
+    file[Header::LEN + 6] = 3;
+    fs::write(&paths[0], file).unwrap();
+
+    let output = inspect(&["decode", "--header", "--schema"], &[&schema, &paths[0]]);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(stdout(&output).ends_with(
+        "0000000b      [2]: struct Item\n\
+         0000000b        id: u32 <-- unexpected end of data at offset 11, needed 1 bytes\n"
+    ));
+
+    let output = inspect(&["decode", "--schema"], &[&schema, &paths[0]]);
+    assert_eq!(output.status.code(), Some(1));
+
+    fs::remove_file(&schema).unwrap();
+    fs::remove_file(&paths[0]).unwrap();
+}
+
+#[test]
+fn diffs_two_files_by_value() {
+    let (schema, paths) = write_files("diff", &[save(7, &[3, 4]), save(7, &[3, 5, 1])]);
+
+    let output = inspect(
+        &["diff", "--header", "--schema"],
+        &[&schema, &paths[0], &paths[0]],
+    );
+    assert!(output.status.success());
+    assert_eq!(stdout(&output), "");
+
+    let output = inspect(
+        &["diff", "--header", "--schema"],
+        &[&schema, &paths[0], &paths[1]],
+    );
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(
+        stdout(&output).lines().skip(2).collect::<Vec<_>>(),
+        [
+            "  00000000  Save: struct Save",
+            "- 00000006    inventory: seq of 2",
+            "+ 00000006    inventory: seq of 3",
+            "  00000009      [1]: struct Item",
+            "- 0000000a        count: u8 = 4",
+            "+ 0000000a        count: u8 = 5",
+            "+ 0000000b      [2]: struct Item",
+            "+ 0000000b        id: u32 = 2",
+            "+ 0000000c        count: u8 = 1",
+        ]
+    );
+
+    fs::remove_file(&schema).unwrap();
+    for path in paths {
+        fs::remove_file(path).unwrap();
+    }
+}
+
+#[test]
+fn escapes_names_from_the_schema() {
+    let schema = temp_path("escape.schema");
+    let mut sbi = SBI::new();
+    sbi.serialize(Schema::Struct {
+        name: "a\nb".into(),
+        fields: vec![Field::new("c\nd", Schema::U8)],
+    });
+    sbi.write_to_path(&schema).unwrap();
+
+    let path = temp_path("escape.sav");
+    let mut sbi = SBI::new();
+    sbi.serialize(5u8);
+    sbi.write_to_path(&path).unwrap();
+
+    let output = inspect(&["hex", "--schema"], &[&schema, &path]);
+    assert!(output.status.success());
+    assert_eq!(
+        stdout(&output),
+        "00000000                                                   a\\nb: struct a\\nb\n\
+         00000000  05                                                 c\\nd: u8 = 5\n"
+    );
+
+    fs::remove_file(&schema).unwrap();
+    fs::remove_file(&path).unwrap();
+}
@@ -0,0 +1,196 @@
+use sbs_api_internal::{
+    DeSerialize, DeserializeError, Migrate, NoPrevious, Reader, Serialize, Versioned, Writer, SBI,
+};
+
+#[derive(Debug, PartialEq)]
+struct SettingsV1 {
+    name: String,
+}
+
+#[derive(Debug, PartialEq)]
+struct SettingsV2 {
+    name: String,
+    retries: u8,
+}
+
+#[derive(Debug, PartialEq)]
+struct SettingsV3 {
+    name: String,
+    retries: u8,
+    verbose: bool,
+}
+
+impl Serialize for SettingsV1 {
+    fn serialize<W: Writer + ?Sized>(&self, writer: &mut W) {
+        self.name.serialize(writer)
+    }
+}
+
+impl<'de> DeSerialize<'de> for SettingsV1 {
+    fn deserialize<R: Reader<'de> + ?Sized>(reader: &mut R) -> Result<Self, DeserializeError> {
+        Ok(Self {
+            name: String::deserialize(reader)?,
+        })
+    }
+}
+
+impl Migrate for SettingsV1 {
+    const VERSION: u32 = 1;
+
+    type Previous = NoPrevious;
+
+    fn migrate(previous: NoPrevious) -> Self {
+        match previous {}
+    }
+}
+
+impl Serialize for SettingsV2 {
+    fn serialize<W: Writer + ?Sized>(&self, writer: &mut W) {
+        (&self.name, self.retries).serialize(writer)
+    }
+}
+
+impl<'de> DeSerialize<'de> for SettingsV2 {
+    fn deserialize<R: Reader<'de> + ?Sized>(reader: &mut R) -> Result<Self, DeserializeError> {
+        let (name, retries) = DeSerialize::deserialize(reader)?;
+        Ok(Self { name, retries })
+    }
+}
+
+impl Migrate for SettingsV2 {
+    const VERSION: u32 = 2;
+
+    type Previous = SettingsV1;
+
+    fn migrate(previous: SettingsV1) -> Self {
+        Self {
+            name: previous.name,
+            retries: 3,
+        }
+    }
+}
+
+impl Serialize for SettingsV3 {
+    fn serialize<W: Writer + ?Sized>(&self, writer: &mut W) {
+        (&self.name, self.retries, self.verbose).serialize(writer)
+    }
+}
+
+impl<'de> DeSerialize<'de> for SettingsV3 {
+    fn deserialize<R: Reader<'de> + ?Sized>(reader: &mut R) -> Result<Self, DeserializeError> {
+        let (name, retries, verbose) = DeSerialize::deserialize(reader)?;
+        Ok(Self {
+            name,
+            retries,
+            verbose,
+        })
+    }
+}
+
+impl Migrate for SettingsV3 {
+    const VERSION: u32 = 3;
+
+    type Previous = SettingsV2;
+
+    fn migrate(previous: SettingsV2) -> Self {
+        Self {
+            name: previous.name,
+            retries: previous.retries,
+            verbose: false,
+        }
+    }
+}
+
+fn encode<T: Migrate + Serialize>(value: T) -> SBI {
+    let mut sbi = SBI::new();
+    sbi.serialize(Versioned(value));
+    sbi
+}
+
+#[test]
+fn old_versions_are_migrated_step_by_step() {
+    let v1 = encode(SettingsV1 { name: "a".into() });
+    let v2 = encode(SettingsV2 {
+        name: "b".into(),
+        retries: 7,
+    });
+    let v3 = encode(SettingsV3 {
+        name: "c".into(),
+        retries: 1,
+        verbose: true,
+    });
+    assert_eq!(v1.data[0], 1);
+
+    let decoded: Vec<SettingsV3> = [v1, v2, v3]
+        .iter()
+        .map(|sbi| {
+            sbi.deserialize::<Versioned<SettingsV3>>()
+                .unwrap()
+                .into_inner()
+        })
+        .collect();
+
+    assert_eq!(
+        decoded,
+        [
+            SettingsV3 {
+                name: "a".into(),
+                retries: 3,
+                verbose: false
+            },
+            SettingsV3 {
+                name: "b".into(),
+                retries: 7,
+                verbose: false
+            },
+            SettingsV3 {
+                name: "c".into(),
+                retries: 1,
+                verbose: true
+            },
+        ]
+    );
+}
+
+#[test]
+fn unknown_versions_are_rejected() {
+    let newer = encode(SettingsV3 {
+        name: "c".into(),
+        retries: 1,
+        verbose: true,
+    });
+    assert_eq!(
+        newer.deserialize::<Versioned<SettingsV2>>().err(),
+        Some(DeserializeError::UnsupportedVersion {
+            offset: 0,
+            version: 3
+        })
+    );
+
+    let mut older = SBI::new();
+    older.data = vec![0];
+    assert_eq!(
+        older.deserialize::<Versioned<SettingsV3>>().err(),
+        Some(DeserializeError::UnsupportedVersion {
+            offset: 0,
+            version: 0
+        })
+    );
+}
+
+#[test]
+fn versioned_values_nest() {
+    let mut sbi = SBI::new();
+    sbi.serialize(vec![(1u8, Versioned(SettingsV1 { name: "x".into() }))]);
+
+    let decoded = sbi
+        .deserialize::<Vec<(u8, Versioned<SettingsV2>)>>()
+        .unwrap();
+    assert_eq!(
+        decoded[0].1 .0,
+        SettingsV2 {
+            name: "x".into(),
+            retries: 3
+        }
+    );
+}
@@ -0,0 +1,446 @@
+use std::{
+    collections::{hash_map::DefaultHasher, BTreeMap, BTreeSet, HashMap, HashSet, VecDeque},
+    fmt::Debug,
+    num::{NonZeroI64, NonZeroU32, NonZeroUsize, Wrapping},
+    rc::Rc,
+    sync::Arc,
+    thread,
+    time::Duration,
+};
+
+use sbs_api_internal::{
+    ByteOrder, ByteSource, Config, DeSerializeOwned, DeserializeError, HashWriter, IntEncoding,
+    IoWriter, Reader, Serialize, SizeCounter, SBI,
+};
+
+fn varint() -> Config {
+    Config {
+        int_encoding: IntEncoding::Varint,
+        ..Config::default()
+    }
+}
+
+fn round_trip<T: Serialize + DeSerializeOwned + PartialEq + Debug>(
+    config: Config,
+    value: T,
+) -> Vec<u8> {
+    let mut sbi = SBI::with_config(config);
+    sbi.serialize(&value);
+    assert_eq!(sbi.deserialize::<T>().unwrap(), value);
+    sbi.data
+}
+
+#[test]
+fn varint_integers_round_trip_at_their_limits() {
+    round_trip(varint(), vec![u16::MIN, u16::MAX]);
+    round_trip(varint(), vec![u32::MIN, 1, 127, 128, 300, u32::MAX]);
+    round_trip(varint(), vec![u64::MIN, u64::MAX]);
+    round_trip(varint(), vec![u128::MIN, u128::MAX]);
+    round_trip(varint(), vec![i16::MIN, -1, 0, 1, i16::MAX]);
+    round_trip(varint(), vec![i64::MIN, -64, 63, i64::MAX]);
+    round_trip(varint(), vec![i128::MIN, i128::MAX]);
+    round_trip(varint(), vec![usize::MIN, usize::MAX]);
+    round_trip(varint(), vec![isize::MIN, isize::MAX]);
+}
+
+#[test]
+fn varint_shrinks_small_values_and_length_prefixes() {
+    assert_eq!(round_trip(varint(), 5u64), [5]);
+    assert_eq!(round_trip(varint(), -1i32), [1]);
+    assert_eq!(round_trip(varint(), 300u32), [0xac, 0x02]);
+    assert_eq!(round_trip(varint(), String::from("hi")), [2, b'h', b'i']);
+    assert_eq!(round_trip(varint(), vec![1u16, 2]), [2, 1, 2]);
+}
+
+#[test]
+fn varint_value_too_wide_for_target_is_out_of_range() {
+    let mut sbi = SBI::with_config(varint());
+    sbi.serialize(70_000u32);
+
+    assert!(sbi.deserialize::<u16>().is_err());
+}
+
+#[test]
+fn byte_order_controls_fixed_width_layout() {
+    let little = Config {
+        byte_order: ByteOrder::Little,
+        ..Config::default()
+    };
+
+    assert_eq!(round_trip(Config::default(), 0x0102_0304u32), [1, 2, 3, 4]);
+    assert_eq!(round_trip(little, 0x0102_0304u32), [4, 3, 2, 1]);
+    assert_eq!(round_trip(little, 1.5f32), 1.5f32.to_le_bytes());
+    assert_eq!(
+        round_trip(little, vec![-2i16]),
+        [1, 0, 0, 0, 0, 0, 0, 0, 0xfe, 0xff]
+    );
+
+    let native = Config {
+        byte_order: ByteOrder::Native,
+        ..Config::default()
+    };
+    assert_eq!(round_trip(native, 7u64), 7u64.to_ne_bytes());
+}
+
+#[test]
+fn sizes_are_written_as_64_bits_on_every_host() {
+    let little = Config {
+        byte_order: ByteOrder::Little,
+        ..Config::default()
+    };
+
+    assert_eq!(
+        round_trip(Config::default(), 0x0102usize),
+        [0, 0, 0, 0, 0, 0, 1, 2]
+    );
+    assert_eq!(round_trip(little, 0x0102usize), [2, 1, 0, 0, 0, 0, 0, 0]);
+    assert_eq!(
+        round_trip(Config::default(), -2isize),
+        [0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xfe]
+    );
+    assert_eq!(
+        round_trip(Config::default(), NonZeroUsize::new(3).unwrap()),
+        [0, 0, 0, 0, 0, 0, 0, 3]
+    );
+    assert_eq!(round_trip(varint(), 300usize), round_trip(varint(), 300u64));
+}
+
+#[test]
+fn sizes_that_do_not_fit_are_out_of_range() {
+    let mut sbi = SBI::new();
+    sbi.serialize(vec![0u64]);
+    assert_eq!(
+        sbi.deserialize::<Vec<NonZeroUsize>>(),
+        Err(DeserializeError::OutOfRange {
+            offset: 8,
+            ty: "NonZeroUsize",
+        })
+    );
+
+    let mut sbi = SBI::new();
+    sbi.serialize(vec![u64::MAX]);
+    let decoded = sbi.deserialize::<Vec<usize>>();
+    if cfg!(target_pointer_width = "64") {
+        assert_eq!(decoded, Ok(vec![usize::MAX]));
+    } else {
+        assert_eq!(
+            decoded,
+            Err(DeserializeError::OutOfRange {
+                offset: 8,
+                ty: "usize",
+            })
+        );
+    }
+
+    let mut sbi = SBI::new();
+    sbi.serialize(vec![i64::MIN]);
+    let decoded = sbi.deserialize::<Vec<isize>>();
+    if cfg!(target_pointer_width = "64") {
+        assert_eq!(decoded, Ok(vec![isize::MIN]));
+    } else {
+        assert_eq!(
+            decoded,
+            Err(DeserializeError::OutOfRange {
+                offset: 8,
+                ty: "isize",
+            })
+        );
+    }
+
+    // wider than the 64 bit wire type, whatever the pointer width
+    let mut sbi = SBI::with_config(varint());
+    sbi.serialize(vec![u128::MAX]);
+    assert_eq!(
+        sbi.deserialize::<Vec<usize>>(),
+        Err(DeserializeError::OutOfRange {
+            offset: 1,
+            ty: "u64",
+        })
+    );
+}
+
+#[test]
+fn std_types_round_trip() {
+    let config = Config::default();
+
+    assert_eq!(round_trip(config, true), [1]);
+    assert_eq!(round_trip(config, 'é'), [0, 0, 0, 0xe9]);
+    assert_eq!(round_trip(config, ()), []);
+    assert_eq!(round_trip(config, None::<u16>), [0]);
+    assert_eq!(round_trip(config, Some(7u16)), [1, 0, 7]);
+    assert_eq!(round_trip(config, [1u8, 2, 3]), [1, 2, 3]);
+
+    round_trip(config, (1u8, String::from("two"), [3i32; 4], Some(false)));
+    round_trip(config, vec![(), (), ()]);
+    round_trip(varint(), vec![Some('\u{10ffff}'), None]);
+}
+
+#[test]
+fn collections_round_trip() {
+    let config = Config::default();
+
+    round_trip(config, VecDeque::from([3u8, 1, 2]));
+    round_trip(
+        config,
+        BTreeSet::from([String::from("a"), String::from("b")]),
+    );
+    round_trip(config, HashSet::from([1u32, 2, 3]));
+    round_trip(config, BTreeMap::from([(1u8, 'x'), (2, 'y')]));
+    round_trip(
+        varint(),
+        HashMap::from([(String::from("k"), vec![1u64, 2])]),
+    );
+
+    // same layout as the equivalent Vec
+    assert_eq!(
+        round_trip(config, VecDeque::from([1u16, 2])),
+        round_trip(config, vec![1u16, 2])
+    );
+}
+
+#[test]
+fn canonical_hash_collections_are_deterministic() {
+    let canonical = Config {
+        canonical: true,
+        ..Config::default()
+    };
+    let sorted = round_trip(
+        canonical,
+        BTreeMap::from_iter((0..64u32).map(|i| (i, i * 2))),
+    );
+
+    for _ in 0..8 {
+        // every map gets a freshly seeded hasher, so iteration order varies
+        let map = HashMap::<u32, u32>::from_iter((0..64u32).map(|i| (i, i * 2)));
+        assert_eq!(round_trip(canonical, map), sorted);
+    }
+}
+
+#[test]
+fn pointers_and_wrappers_round_trip() {
+    let config = Config::default();
+
+    assert_eq!(round_trip(config, Box::new(5u8)), [5]);
+    round_trip(config, Rc::new(String::from("rc")));
+    round_trip(config, Arc::<str>::from("arc"));
+    round_trip(config, Box::<[u16]>::from([1, 2, 3]));
+    round_trip(config, Wrapping(200u8));
+    round_trip(config, NonZeroU32::new(9).unwrap());
+    round_trip(varint(), NonZeroI64::new(-9).unwrap());
+    round_trip(config, Duration::new(12, 999_999_999));
+
+    // pointers encode exactly like their contents
+    assert_eq!(
+        round_trip(config, Arc::<str>::from("same")),
+        round_trip(config, String::from("same"))
+    );
+}
+
+#[test]
+fn tracked_references_keep_their_sharing() {
+    let tracked = Config {
+        track_refs: true,
+        ..Config::default()
+    };
+
+    let shared = Rc::new(String::from("shared"));
+    let other = Rc::new(String::from("shared"));
+    let graph = vec![shared.clone(), other.clone(), shared.clone(), shared];
+
+    let mut sbi = SBI::with_config(tracked);
+    sbi.serialize(&graph);
+    let back: Vec<Rc<String>> = sbi.deserialize().unwrap();
+
+    assert_eq!(back, graph);
+    assert!(Rc::ptr_eq(&back[0], &back[2]));
+    assert!(Rc::ptr_eq(&back[0], &back[3]));
+    assert!(!Rc::ptr_eq(&back[0], &back[1]));
+
+    // the payload is only written once per allocation
+    let untracked = round_trip(Config::default(), graph.clone());
+    assert_eq!(sbi.data.len(), untracked.len() - 2 * (8 + 6) + 4);
+
+    let name: Arc<str> = Arc::from("node");
+    let pair = (name.clone(), Some(name));
+    let mut sbi = SBI::with_config(tracked);
+    sbi.serialize(&pair);
+    let (first, second): (Arc<str>, Option<Arc<str>>) = sbi.deserialize().unwrap();
+    assert!(Arc::ptr_eq(&first, &second.unwrap()));
+}
+
+#[test]
+fn untracked_references_are_copied() {
+    let shared = Rc::new(5u32);
+
+    let mut sbi = SBI::new();
+    sbi.serialize(vec![shared.clone(), shared]);
+    let back: Vec<Rc<u32>> = sbi.deserialize().unwrap();
+
+    assert!(!Rc::ptr_eq(&back[0], &back[1]));
+}
+
+#[test]
+fn size_counter_matches_the_serialized_length() {
+    let value = (
+        vec![1u32, 2, 3],
+        "name".to_string(),
+        Some(Duration::from_millis(5)),
+    );
+
+    for config in [Config::default(), varint()] {
+        let mut sbi = SBI::with_config(config);
+        sbi.serialize(&value);
+        assert_eq!(SizeCounter::size_of(&value, config), sbi.data.len() as u64);
+    }
+}
+
+#[test]
+fn hash_writer_fingerprints_the_encoding() {
+    let hash = |value: &BTreeMap<u8, String>| {
+        let mut writer = HashWriter::new(DefaultHasher::new(), Config::default());
+        writer.serialize(value);
+        writer.finish()
+    };
+
+    let a = BTreeMap::from([(1, "one".to_string()), (2, "two".to_string())]);
+    let b = BTreeMap::from([(2, "two".to_string()), (1, "one".to_string())]);
+    let c = BTreeMap::from([(1, "one".to_string())]);
+    assert_eq!(hash(&a), hash(&b));
+    assert_ne!(hash(&a), hash(&c));
+}
+
+#[test]
+fn byte_source_decodes_borrowed_values_from_any_bytes() {
+    let mut sbi = SBI::with_config(varint());
+    sbi.serialize(("borrowed", 300u32));
+
+    let source = ByteSource::with_config(&sbi.data[..], varint());
+    assert_eq!(
+        source.deserialize::<(&str, u32)>().unwrap(),
+        ("borrowed", 300)
+    );
+
+    let source = ByteSource::with_config(sbi.data.clone(), varint());
+    assert_eq!(
+        source.deserialize::<(String, u32)>().unwrap(),
+        ("borrowed".to_string(), 300)
+    );
+}
+
+#[test]
+fn io_writer_fills_a_fixed_buffer() {
+    let mut buffer = [0u8; 12];
+    let mut writer = IoWriter::new(&mut buffer[..]);
+    writer.serialize(7u64).unwrap();
+    assert_eq!(writer.written(), 8);
+    assert!(writer.serialize(7u64).is_err());
+}
+
+#[test]
+fn cursor_decodes_consecutive_values_and_reports_the_rest() {
+    let mut sbi = SBI::new();
+    sbi.serialize(1u32);
+    sbi.serialize("two");
+    sbi.serialize(3u8);
+    sbi.data.extend_from_slice(&[0xaa, 0xbb]);
+
+    let mut cursor = sbi.cursor();
+    assert_eq!(cursor.deserialize::<u32>().unwrap(), 1);
+    assert_eq!(cursor.deserialize::<&str>().unwrap(), "two");
+    assert_eq!(cursor.peek(1).unwrap(), [3]);
+    cursor.skip(1).unwrap();
+    assert_eq!(cursor.position(), 16);
+    assert_eq!(cursor.remaining(), 2);
+    assert_eq!(cursor.rest(), [0xaa, 0xbb]);
+    assert!(cursor.skip(3).is_err());
+}
+
+#[test]
+fn shared_ids_restart_with_each_root_on_a_cursor() {
+    let config = Config {
+        track_refs: true,
+        ..Config::default()
+    };
+    let shared = Rc::new(9u16);
+
+    let mut sbi = SBI::with_config(config);
+    sbi.serialize((shared.clone(), shared.clone()));
+    sbi.serialize((shared.clone(), shared));
+
+    let mut cursor = sbi.cursor();
+    for _ in 0..2 {
+        let (a, b) = cursor.deserialize::<(Rc<u16>, Rc<u16>)>().unwrap();
+        assert!(Rc::ptr_eq(&a, &b));
+    }
+    assert!(cursor.is_empty());
+}
+
+#[test]
+fn iter_yields_every_appended_record() {
+    let mut sbi = SBI::with_config(varint());
+    let records = [
+        (1u64, "a".to_string()),
+        (2, "bb".to_string()),
+        (300, String::new()),
+    ];
+    for record in &records {
+        sbi.serialize(record);
+    }
+
+    let decoded: Vec<(u64, String)> = sbi.iter().collect::<Result<_, _>>().unwrap();
+    assert_eq!(decoded, records);
+    assert_eq!(SBI::new().iter::<u8>().count(), 0);
+}
+
+#[test]
+fn regions_of_a_shared_buffer_decode_concurrently() {
+    let mut sbi = SBI::new();
+    let mut regions = Vec::new();
+    for i in 0..8u32 {
+        let start = sbi.data.len();
+        sbi.serialize((i, vec![i; i as usize]));
+        regions.push(start..sbi.data.len());
+    }
+
+    let sbi = Arc::new(sbi);
+    let decoded: Vec<(u32, Vec<u32>)> = thread::scope(|scope| {
+        let workers: Vec<_> = regions
+            .iter()
+            .map(|region| {
+                let sbi = Arc::clone(&sbi);
+                scope.spawn(move || {
+                    let mut cursor = sbi.region(region.clone());
+                    let value = cursor.deserialize().unwrap();
+                    cursor.end().unwrap();
+                    value
+                })
+            })
+            .collect();
+        workers
+            .into_iter()
+            .map(|worker| worker.join().unwrap())
+            .collect()
+    });
+
+    for (i, (n, items)) in decoded.into_iter().enumerate() {
+        assert_eq!((n as usize, items.len()), (i, i));
+    }
+}
+
+#[test]
+fn region_errors_report_offsets_into_the_whole_buffer() {
+    let mut sbi = SBI::new();
+    sbi.serialize(1u32);
+    sbi.serialize(2u32);
+
+    let source = ByteSource::new(&sbi.data[..]);
+    assert_eq!(source.region(4..8).deserialize::<u32>().unwrap(), 2);
+    assert_eq!(
+        source
+            .region(4..6)
+            .deserialize::<u32>()
+            .unwrap_err()
+            .offset(),
+        4
+    );
+}
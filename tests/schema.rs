@@ -0,0 +1,209 @@
+use std::rc::Rc;
+
+use sbs_api_internal::{
+    Config, DeSerialize, Describe, DeserializeError, Field, IntEncoding, Schema, SchemaMismatch,
+    Serialize, Variant, SBI,
+};
+
+#[derive(Serialize, DeSerialize, Describe, Debug, PartialEq)]
+#[repr(u8)]
+enum Shape {
+    Point,
+    Circle(f32),
+    Rect { w: u16, h: u16 } = 5,
+}
+
+#[derive(Serialize, DeSerialize, Describe, Debug, PartialEq)]
+struct Scene {
+    name: String,
+    shapes: Vec<Shape>,
+    tags: Option<[u8; 2]>,
+}
+
+#[derive(Serialize, DeSerialize, Describe, Debug, PartialEq)]
+#[sbs(tagged)]
+struct Prefs {
+    #[sbs(id = 3)]
+    volume: u8,
+    #[sbs(id = 2)]
+    theme: Rc<String>,
+}
+
+fn scene() -> Scene {
+    Scene {
+        name: "demo".into(),
+        shapes: vec![Shape::Point, Shape::Circle(1.0), Shape::Rect { w: 2, h: 3 }],
+        tags: Some([7, 8]),
+    }
+}
+
+#[test]
+fn derived_schemas_describe_the_layout() {
+    assert_eq!(
+        Shape::schema(),
+        Schema::Enum {
+            name: "Shape".into(),
+            variants: vec![
+                Variant::new("Point", 0, vec![]),
+                Variant::new("Circle", 1, vec![Field::new("0", Schema::F32)]),
+                Variant::new(
+                    "Rect",
+                    5,
+                    vec![Field::new("w", Schema::U16), Field::new("h", Schema::U16)]
+                ),
+            ],
+        }
+    );
+    assert_eq!(
+        Prefs::schema(),
+        Schema::Tagged {
+            name: "Prefs".into(),
+            fields: vec![
+                (3, Field::new("volume", Schema::U8)),
+                (
+                    2,
+                    Field::new("theme", Schema::Shared(Box::new(Schema::String)))
+                ),
+            ],
+        }
+    );
+}
+
+#[test]
+fn matching_buffers_validate() {
+    for config in [
+        Config::default(),
+        Config {
+            int_encoding: IntEncoding::Varint,
+            track_refs: true,
+            ..Config::default()
+        },
+    ] {
+        let mut sbi = SBI::with_config(config);
+        sbi.serialize(scene());
+        Scene::schema().validate(&sbi).unwrap();
+
+        let mut sbi = SBI::with_config(config);
+        sbi.serialize(Prefs {
+            volume: 3,
+            theme: Rc::new("dark".into()),
+        });
+        Prefs::schema().validate(&sbi).unwrap();
+    }
+}
+
+#[test]
+fn first_mismatch_is_reported_with_path_and_offset() {
+    let mut sbi = SBI::new();
+    sbi.serialize(scene());
+
+    // discriminant of the last shape
+    let offset = 8 + 4 + 8 + 1 + 1 + 4;
+    sbi.data[offset] = 4;
+    assert_eq!(
+        Scene::schema().validate(&sbi),
+        Err(SchemaMismatch {
+            path: "Scene.shapes[2]".into(),
+            error: DeserializeError::InvalidDiscriminant {
+                offset,
+                discriminant: 4
+            },
+        })
+    );
+
+    sbi.data[offset] = 5;
+    sbi.data.push(0);
+    assert_eq!(
+        Scene::schema().validate(&sbi).unwrap_err().error,
+        DeserializeError::TrailingBytes {
+            offset: sbi.data.len() - 1,
+            len: 1
+        }
+    );
+
+    sbi.data.truncate(sbi.data.len() - 2);
+    // the array is checked against the remaining data before its elements
+    let mismatch = Scene::schema().validate(&sbi).unwrap_err();
+    assert_eq!(mismatch.path, "Scene.tags?");
+    assert_eq!(
+        mismatch.error,
+        DeserializeError::LengthOverflow {
+            offset: sbi.data.len() - 1,
+            len: 2
+        }
+    );
+}
+
+#[test]
+fn schemas_can_be_embedded_and_exported() {
+    let mut sbi = SBI::new();
+    sbi.serialize(Scene::schema());
+    assert_eq!(sbi.deserialize::<Schema>().unwrap(), Scene::schema());
+
+    assert_eq!(
+        <(u8, Option<String>)>::schema().to_json(&Config::default()),
+        concat!(
+            r#"{"byte_order":"big","int_encoding":"fixed","track_refs":false,"schema":"#,
+            r#"{"type":"tuple","items":[{"type":"u8"},{"type":"option","item":{"type":"string"}}]}}"#
+        )
+    );
+    assert!(Prefs::schema()
+        .to_json(&Config::default())
+        .contains(r#"{"name":"volume","id":3,"#));
+}
+
+#[test]
+fn dump_lists_every_value_and_marks_the_failure() {
+    let mut sbi = SBI::new();
+    sbi.serialize(scene());
+
+    let dump = sbi.dump::<Scene>();
+    assert_eq!(dump.mismatch(), None);
+    assert_eq!(
+        dump.text(),
+        "\
+00000000  Scene: struct Scene
+00000000    name: string = \"demo\"
+0000000c    shapes: seq of 3
+00000014      [0]: enum Shape::Point
+00000015      [1]: enum Shape::Circle
+00000016        0: f32 = 1.0
+0000001a      [2]: enum Shape::Rect
+0000001b        w: u16 = 2
+0000001d        h: u16 = 3
+0000001f    tags: option = Some
+00000020      ?: array of 2
+00000020        [0]: u8 = 7
+00000021        [1]: u8 = 8
+"
+    );
+
+    sbi.data[0x1a] = 4;
+    let dump = sbi.dump::<Scene>();
+    assert_eq!(dump.mismatch().unwrap().path, "Scene.shapes[2]");
+    assert!(dump.text().ends_with(
+        "00000016        0: f32 = 1.0\n\
+         0000001a      [2]: enum <-- invalid enum discriminant 4 at offset 26\n"
+    ));
+}
+
+#[test]
+fn dump_follows_tagged_fields() {
+    let mut sbi = SBI::with_config(Config {
+        track_refs: true,
+        ..Config::default()
+    });
+    sbi.serialize(Prefs {
+        volume: 3,
+        theme: Rc::new("dark".into()),
+    });
+
+    assert_eq!(
+        sbi.dump::<Prefs>().text(),
+        "\
+00000000  Prefs: tagged Prefs
+00000003    volume: u8 = 3
+00000006    theme: string = \"dark\"
+"
+    );
+}
@@ -0,0 +1,276 @@
+use std::{
+    io::{self, Read, Write},
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use sbs_api_internal::{
+    Config, DeSerialize, DeserializeError, IntEncoding, IoWriter, Reader, StreamError,
+    StreamReader, SBI,
+};
+
+/// Hands out at most `step` bytes per read, like a slow pipe.
+struct Trickle<'a> {
+    data: &'a [u8],
+    step: usize,
+}
+
+impl Read for Trickle<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.step.min(buf.len()).min(self.data.len());
+        buf[..n].copy_from_slice(&self.data[..n]);
+        self.data = &self.data[n..];
+        Ok(n)
+    }
+}
+
+fn records(config: Config) -> (Vec<(u32, String)>, Vec<u8>) {
+    let records = (0..200u32)
+        .map(|i| (i, "x".repeat(i as usize % 37)))
+        .collect::<Vec<_>>();
+
+    let mut sbi = SBI::with_config(config);
+    for record in &records {
+        sbi.serialize(record);
+    }
+
+    (records, sbi.data)
+}
+
+#[test]
+fn reads_every_record_from_a_slow_source() {
+    let (expected, data) = records(Config::default());
+
+    for step in [1, 3, 64, usize::MAX] {
+        let mut reader = StreamReader::new(Trickle { data: &data, step });
+        let decoded = reader
+            .iter::<(u32, String)>()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+
+        assert_eq!(decoded, expected);
+        assert_eq!(reader.position(), data.len());
+    }
+}
+
+#[test]
+fn honours_the_config() {
+    let config = Config {
+        int_encoding: IntEncoding::Varint,
+        ..Config::default()
+    };
+    let (expected, data) = records(config);
+
+    let mut reader = StreamReader::with_config(data.as_slice(), config);
+    assert_eq!(
+        reader.read::<(u32, String)>().unwrap(),
+        Some(expected[0].clone())
+    );
+    assert_eq!(reader.iter::<(u32, String)>().count(), expected.len() - 1);
+}
+
+#[test]
+fn truncated_final_record_reports_stream_offset() {
+    let mut sbi = SBI::new();
+    sbi.serialize(1u32);
+    sbi.serialize(String::from("abc"));
+    sbi.data.truncate(sbi.data.len() - 1);
+
+    let mut reader = StreamReader::new(Trickle {
+        data: &sbi.data,
+        step: 2,
+    });
+    assert_eq!(reader.read::<u32>().unwrap(), Some(1));
+
+    match reader.read::<String>() {
+        Err(StreamError::Deserialize(DeserializeError::LengthOverflow { offset, len })) => {
+            assert_eq!((offset, len), (4, 3))
+        }
+        other => panic!("unexpected result {other:?}"),
+    }
+}
+
+#[test]
+fn empty_values_do_not_loop_forever() {
+    let mut reader = StreamReader::new(&[1u8, 2, 3][..]);
+    assert_eq!(reader.read::<u8>().unwrap(), Some(1));
+
+    let values: Vec<_> = reader.iter::<()>().collect();
+    match &values[..] {
+        [Err(StreamError::Deserialize(DeserializeError::TrailingBytes { offset, len }))] => {
+            assert_eq!((*offset, *len), (1, 2))
+        }
+        other => panic!("unexpected result {other:?}"),
+    }
+
+    // the bytes are still there for a type that does consume them
+    assert_eq!(reader.read::<u16>().unwrap(), Some(0x0203));
+    assert_eq!(reader.iter::<()>().count(), 0);
+}
+
+#[test]
+fn io_errors_are_passed_through() {
+    struct Broken;
+
+    impl Read for Broken {
+        fn read(&mut self, _: &mut [u8]) -> io::Result<usize> {
+            Err(io::Error::other("unplugged"))
+        }
+    }
+
+    let mut reader = StreamReader::new(Broken);
+    assert!(matches!(reader.read::<u8>(), Err(StreamError::Io(_))));
+}
+
+#[test]
+fn io_writer_matches_in_memory_output() {
+    let (records, expected) = records(Config::default());
+
+    let mut writer = IoWriter::new(Vec::new());
+    for record in &records {
+        writer.serialize(record).unwrap();
+    }
+
+    assert_eq!(writer.written(), expected.len() as u64);
+    let written = writer.into_inner();
+    assert_eq!(written, expected);
+
+    let mut reader = StreamReader::new(written.as_slice());
+    assert_eq!(reader.iter::<(u32, String)>().count(), records.len());
+}
+
+#[test]
+fn io_writer_reports_the_first_error() {
+    struct Full(usize);
+
+    impl Write for Full {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            if self.0 == 0 {
+                return Err(io::Error::new(io::ErrorKind::StorageFull, "disk full"));
+            }
+
+            let n = self.0.min(buf.len());
+            self.0 -= n;
+            Ok(n)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    let mut writer = IoWriter::new(Full(10));
+    assert!(writer.serialize(5u64).is_ok());
+
+    let err = writer.serialize(vec![1u32, 2, 3]).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::StorageFull);
+    assert_eq!(writer.written(), 8);
+}
+
+#[test]
+fn large_values_are_not_decoded_once_per_read() {
+    static ATTEMPTS: AtomicUsize = AtomicUsize::new(0);
+
+    struct Counted(Vec<u64>);
+
+    impl<'de> DeSerialize<'de> for Counted {
+        fn deserialize<R: Reader<'de> + ?Sized>(reader: &mut R) -> Result<Self, DeserializeError> {
+            ATTEMPTS.fetch_add(1, Ordering::Relaxed);
+            Vec::deserialize(reader).map(Counted)
+        }
+    }
+
+    let values: Vec<u64> = (0..1 << 19).collect();
+    let mut sbi = SBI::new();
+    sbi.serialize(&values);
+
+    let mut reader = StreamReader::new(Trickle {
+        data: &sbi.data,
+        step: 64 * 1024,
+    });
+    let decoded = reader.read::<Counted>().unwrap().unwrap();
+
+    assert_eq!(decoded.0, values);
+    // 4 MiB arriving 64 KiB at a time would take 64 attempts if every read
+    // was followed by another one
+    assert!(ATTEMPTS.load(Ordering::Relaxed) <= 12);
+}
+
+#[test]
+fn small_records_after_a_large_one_keep_their_stream_offsets() {
+    let mut sbi = SBI::new();
+    sbi.serialize(vec![7u8; 100_000]);
+    for i in 0..5_000u16 {
+        sbi.serialize(Some(i));
+    }
+    let corrupt = sbi.data.len();
+    sbi.serialize(Some(0u16));
+    sbi.data[corrupt] = 9;
+
+    for step in [4096, usize::MAX] {
+        let mut reader = StreamReader::new(Trickle {
+            data: &sbi.data,
+            step,
+        });
+        assert_eq!(reader.read::<Vec<u8>>().unwrap().unwrap().len(), 100_000);
+        for i in 0..5_000u16 {
+            assert_eq!(reader.read::<Option<u16>>().unwrap(), Some(Some(i)));
+        }
+        assert_eq!(reader.position(), corrupt);
+
+        match reader.read::<Option<u16>>() {
+            Err(StreamError::Deserialize(err)) => assert_eq!(
+                err,
+                DeserializeError::InvalidTag {
+                    offset: corrupt,
+                    tag: 9
+                }
+            ),
+            other => panic!("unexpected result {other:?}"),
+        }
+    }
+}
+
+#[test]
+fn impossible_length_prefixes_fail_without_reading_ahead() {
+    /// A corrupt length prefix in front of 64 MiB of further data, counting
+    /// the bytes handed out.
+    struct Corrupt {
+        data: io::Chain<io::Cursor<[u8; 8]>, io::Take<io::Repeat>>,
+        read: usize,
+    }
+
+    impl Corrupt {
+        fn new(len: u64) -> Self {
+            Self {
+                data: io::Cursor::new(len.to_be_bytes()).chain(io::repeat(0).take(64 << 20)),
+                read: 0,
+            }
+        }
+    }
+
+    impl Read for Corrupt {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            let n = self.data.read(buf)?;
+            self.read += n;
+            Ok(n)
+        }
+    }
+
+    let too_large = |len| DeserializeError::LengthTooLarge { offset: 0, len };
+
+    // more bytes than a buffer can hold
+    let mut reader = StreamReader::new(Corrupt::new(u64::MAX / 4));
+    match reader.read::<Vec<[u8; 8]>>() {
+        Err(StreamError::Deserialize(err)) => assert_eq!(err, too_large(u64::MAX / 4)),
+        other => panic!("unexpected result {other:?}"),
+    }
+    assert!(reader.into_inner().read <= 64 << 10);
+
+    // more empty elements than allowed
+    let mut reader = StreamReader::new(Corrupt::new(1 << 20));
+    match reader.read::<Vec<()>>() {
+        Err(StreamError::Deserialize(err)) => assert_eq!(err, too_large(1 << 20)),
+        other => panic!("unexpected result {other:?}"),
+    }
+    assert!(reader.into_inner().read <= 64 << 10);
+}
@@ -0,0 +1,154 @@
+use std::rc::Rc;
+
+use sbs_api_internal::{
+    Config, DeSerialize, DeserializeError, IntEncoding, Serialize, SizeCounter, SBI,
+};
+
+#[derive(Serialize, DeSerialize, Debug, PartialEq)]
+#[sbs(tagged)]
+struct SaveV1 {
+    #[sbs(id = 1)]
+    name: String,
+    #[sbs(id = 2)]
+    level: u32,
+    #[sbs(id = 7)]
+    inventory: Vec<String>,
+}
+
+#[derive(Serialize, DeSerialize, Debug, PartialEq)]
+#[sbs(tagged)]
+struct SaveV2 {
+    #[sbs(id = 1)]
+    name: String,
+    // field 2 (`level`) was dropped
+    #[sbs(id = 7)]
+    inventory: Vec<String>,
+    #[sbs(id = 8, default = "default_volume")]
+    volume: f32,
+    #[sbs(id = 9)]
+    hardcore: bool,
+}
+
+fn default_volume() -> f32 {
+    0.5
+}
+
+#[derive(Serialize, DeSerialize, Debug, PartialEq)]
+#[sbs(tagged)]
+struct Pair<'a>(#[sbs(id = 1)] &'a str, #[sbs(id = 5)] Option<u8>);
+
+fn encode<T: sbs_api_internal::Serialize>(config: Config, value: T) -> SBI {
+    let mut sbi = SBI::with_config(config);
+    sbi.serialize(value);
+    sbi
+}
+
+#[test]
+fn older_readers_skip_new_fields_and_newer_readers_default_missing_ones() {
+    let v1 = SaveV1 {
+        name: "ada".into(),
+        level: 12,
+        inventory: vec!["lamp".into()],
+    };
+    let v2 = SaveV2 {
+        name: "bob".into(),
+        inventory: vec![],
+        volume: 0.9,
+        hardcore: true,
+    };
+
+    for config in [
+        Config::default(),
+        Config {
+            int_encoding: IntEncoding::Varint,
+            ..Config::default()
+        },
+    ] {
+        let old_file = encode(config, &v1);
+        assert_eq!(old_file.deserialize::<SaveV1>().unwrap(), v1);
+        assert_eq!(
+            old_file.deserialize::<SaveV2>().unwrap(),
+            SaveV2 {
+                name: "ada".into(),
+                inventory: vec!["lamp".into()],
+                volume: 0.5,
+                hardcore: false,
+            }
+        );
+
+        let new_file = encode(config, &v2);
+        assert_eq!(new_file.deserialize::<SaveV2>().unwrap(), v2);
+        assert_eq!(
+            new_file.deserialize::<SaveV1>().unwrap(),
+            SaveV1 {
+                name: "bob".into(),
+                level: 0,
+                inventory: vec![]
+            }
+        );
+    }
+}
+
+#[test]
+fn tuple_structs_borrow_through_tagged_fields() {
+    let sbi = encode(Config::default(), Pair("hi", Some(3)));
+    assert_eq!(
+        sbi.data,
+        [2, 1, 10, 0, 0, 0, 0, 0, 0, 0, 2, b'h', b'i', 5, 2, 1, 3]
+    );
+    assert_eq!(sbi.deserialize::<Pair>().unwrap(), Pair("hi", Some(3)));
+}
+
+#[test]
+fn field_errors_point_into_the_whole_buffer() {
+    let mut sbi = encode(Config::default(), Pair("hi", Some(3)));
+    sbi.data[15] = 9;
+    assert_eq!(
+        sbi.deserialize::<Pair>(),
+        Err(DeserializeError::InvalidTag { offset: 15, tag: 9 })
+    );
+
+    sbi.data[14] = 200;
+    assert!(matches!(
+        sbi.deserialize::<Pair>(),
+        Err(DeserializeError::LengthOverflow { offset: 14, .. })
+    ));
+}
+
+#[derive(Serialize, DeSerialize, Debug, PartialEq, Default)]
+#[sbs(tagged)]
+struct Slot {
+    #[sbs(id = 1)]
+    items: Vec<Rc<String>>,
+}
+
+#[derive(Serialize, DeSerialize, Debug, PartialEq)]
+#[sbs(tagged)]
+struct Inventory {
+    #[sbs(id = 1)]
+    slot: Slot,
+    #[sbs(id = 2)]
+    owner: Rc<String>,
+}
+
+#[test]
+fn nested_tagged_structs_round_trip_with_reference_tracking() {
+    let lamp = Rc::new(String::from("lamp"));
+    let inventory = Inventory {
+        slot: Slot {
+            items: vec![lamp.clone(), lamp.clone()],
+        },
+        owner: lamp,
+    };
+
+    let config = Config {
+        track_refs: true,
+        ..Config::default()
+    };
+    let sbi = encode(config, &inventory);
+    assert_eq!(
+        sbi.data.len() as u64,
+        SizeCounter::size_of(&inventory, config)
+    );
+    assert_eq!(sbi.deserialize::<Inventory>().unwrap(), inventory);
+}